@@ -0,0 +1,495 @@
+use std::f32::consts::PI;
+
+use glam::{Quat, Vec3};
+
+use crate::{color::Color, graphics::Camera3d, object::Object, time::TimeStep, transform::Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    Step,
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    BounceIn,
+    BounceOut,
+}
+
+impl Easing {
+    /// Maps a normalized time in [0, 1] to an interpolation factor
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            },
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2.0_f32).powf(10.0 * t - 10.0) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+                }
+            },
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    (2.0_f32).powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            },
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Values that can be animated
+pub trait Interpolate: Copy {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Quat {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.slerp(*other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LoopMode {
+    #[default]
+    Once,
+    Loop,
+    PingPong,
+}
+
+impl LoopMode {
+    /// Wraps a time that may exceed `duration` according to the loop mode
+    pub fn wrap(&self, time: f32, duration: f32) -> f32 {
+        if duration <= 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            LoopMode::Once => time.clamp(0.0, duration),
+            LoopMode::Loop => time.rem_euclid(duration),
+            LoopMode::PingPong => {
+                let t = time.rem_euclid(2.0 * duration);
+                if t > duration { 2.0 * duration - t } else { t }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    /// Easing of the segment going from this keyframe to the next one
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self {
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_keyframe(mut self, time: f32, value: T, easing: Easing) -> Self {
+        self.add_keyframe(time, value, easing);
+        self
+    }
+
+    pub fn add_keyframe(&mut self, time: f32, value: T, easing: Easing) -> &mut Self {
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(index, Keyframe { time, value, easing });
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next >= self.keyframes.len() {
+            return self.keyframes.last().map(|k| k.value);
+        }
+
+        let from = &self.keyframes[next - 1];
+        let to = &self.keyframes[next];
+        let t = (time - from.time) / (to.time - from.time);
+        Some(from.value.interpolate(&to.value, from.easing.apply(t)))
+    }
+}
+
+/// A set of keyframe tracks played together
+#[derive(Debug, Clone)]
+pub struct Animation {
+    position: Option<Track<Vec3>>,
+    rotation: Option<Track<Quat>>,
+    scale: Option<Track<Vec3>>,
+    color: Option<Track<Color>>,
+    morph_weights: Vec<(usize, Track<f32>)>,
+    loop_mode: LoopMode,
+    speed: f32,
+    time: f32,
+    playing: bool,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            position: None,
+            rotation: None,
+            scale: None,
+            color: None,
+            morph_weights: Vec::new(),
+            loop_mode: LoopMode::Once,
+            speed: 1.0,
+            time: 0.0,
+            playing: true,
+        }
+    }
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_position(mut self, track: Track<Vec3>) -> Self {
+        self.position = Some(track);
+        self
+    }
+
+    pub fn with_rotation(mut self, track: Track<Quat>) -> Self {
+        self.rotation = Some(track);
+        self
+    }
+
+    pub fn with_scale(mut self, track: Track<Vec3>) -> Self {
+        self.scale = Some(track);
+        self
+    }
+
+    pub fn with_color(mut self, track: Track<Color>) -> Self {
+        self.color = Some(track);
+        self
+    }
+
+    /// Animates the weight of the morph target `index` of the object
+    pub fn with_morph_weight(mut self, index: usize, track: Track<f32>) -> Self {
+        self.morph_weights.push((index, track));
+        self
+    }
+
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn play(&mut self) -> &mut Self {
+        self.playing = true;
+        self
+    }
+
+    pub fn pause(&mut self) -> &mut Self {
+        self.playing = false;
+        self
+    }
+
+    pub fn seek(&mut self, time: f32) -> &mut Self {
+        self.time = time;
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        [
+            self.position.as_ref().map(|t| t.duration()),
+            self.rotation.as_ref().map(|t| t.duration()),
+            self.scale.as_ref().map(|t| t.duration()),
+            self.color.as_ref().map(|t| t.duration()),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.morph_weights.iter().map(|(_, t)| t.duration()))
+        .fold(0.0, f32::max)
+    }
+
+    /// Current time in the animation, after looping
+    pub fn time(&self) -> f32 {
+        self.loop_mode.wrap(self.time, self.duration())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.loop_mode == LoopMode::Once && self.time >= self.duration()
+    }
+
+    pub fn advance(&mut self, dt: f32) -> &mut Self {
+        if self.playing {
+            self.time += dt * self.speed;
+        }
+        self
+    }
+
+    pub fn update(&mut self, time_step: &TimeStep) -> &mut Self {
+        self.advance(time_step.delta_time())
+    }
+
+    pub fn position(&self) -> Option<Vec3> {
+        self.position.as_ref().and_then(|t| t.sample(self.time()))
+    }
+
+    pub fn rotation(&self) -> Option<Quat> {
+        self.rotation.as_ref().and_then(|t| t.sample(self.time()))
+    }
+
+    pub fn scale(&self) -> Option<Vec3> {
+        self.scale.as_ref().and_then(|t| t.sample(self.time()))
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color.as_ref().and_then(|t| t.sample(self.time()))
+    }
+
+    /// Overrides the animated channels of `base`
+    pub fn sample_transform(&self, base: &Transform) -> Transform {
+        Transform {
+            translation: self.position().unwrap_or(base.translation),
+            rotation: self.rotation().unwrap_or(base.rotation),
+            scale: self.scale().unwrap_or(base.scale),
+        }
+    }
+
+    pub fn apply_to_object(&self, object: &mut Object) {
+        let transform = self.sample_transform(&Transform::from_matrix(object.transform()));
+        object.set_transform(&transform.matrix());
+        if let Some(color) = self.color() {
+            object.set_color(color);
+        }
+
+        if !self.morph_weights.is_empty() {
+            let mut weights = object.morph_weights().to_vec();
+            for (index, track) in self.morph_weights.iter() {
+                if let (Some(weight), Some(w)) = (track.sample(self.time()), weights.get_mut(*index)) {
+                    *w = weight;
+                }
+            }
+            object.set_morph_weights(&weights);
+        }
+    }
+
+    pub fn apply_to_camera(&self, camera: &mut Camera3d) {
+        let transform = self.sample_transform(&Transform::from_matrix(camera.transform()));
+        camera.set_transform(&transform.matrix());
+    }
+}
+
+/// Interpolates a single value between two states
+#[derive(Debug, Clone, Copy)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    duration: f32,
+    easing: Easing,
+    loop_mode: LoopMode,
+    elapsed: f32,
+}
+
+impl<T: Interpolate> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::Linear,
+            loop_mode: LoopMode::Once,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    pub fn restart(&mut self) -> &mut Self {
+        self.elapsed = 0.0;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.loop_mode == LoopMode::Once && self.elapsed >= self.duration
+    }
+
+    pub fn value(&self) -> T {
+        if self.duration <= 0.0 {
+            return self.to;
+        }
+
+        let t = self.loop_mode.wrap(self.elapsed, self.duration) / self.duration;
+        self.from.interpolate(&self.to, self.easing.apply(t))
+    }
+
+    pub fn advance(&mut self, dt: f32) -> T {
+        self.elapsed += dt;
+        self.value()
+    }
+
+    pub fn update(&mut self, time_step: &TimeStep) -> T {
+        self.advance(time_step.delta_time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    #[test]
+    fn easings_start_and_end_on_the_keyframes() {
+        let easings = [
+            Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+            Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+            Easing::ElasticIn, Easing::ElasticOut, Easing::BounceIn, Easing::BounceOut,
+        ];
+        for easing in easings {
+            assert!(easing.apply(0.0).abs() < 1.0e-5, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1.0e-5, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn sampling_a_track() {
+        let track = Track::new()
+            .with_keyframe(1.0, vec3(2.0, 0.0, 0.0), Easing::Linear)
+            .with_keyframe(0.0, vec3(0.0, 0.0, 0.0), Easing::Linear);
+        assert_eq!(track.duration(), 1.0);
+        assert_eq!(track.sample(-1.0), Some(vec3(0.0, 0.0, 0.0)));
+        assert_eq!(track.sample(0.25), Some(vec3(0.5, 0.0, 0.0)));
+        assert_eq!(track.sample(3.0), Some(vec3(2.0, 0.0, 0.0)));
+        assert_eq!(Track::<f32>::new().sample(0.0), None);
+    }
+
+    #[test]
+    fn loop_modes() {
+        assert_eq!(LoopMode::Once.wrap(3.0, 2.0), 2.0);
+        assert_eq!(LoopMode::Loop.wrap(3.0, 2.0), 1.0);
+        assert_eq!(LoopMode::PingPong.wrap(3.0, 2.0), 1.0);
+        assert_eq!(LoopMode::PingPong.wrap(3.5, 2.0), 0.5);
+    }
+
+    #[test]
+    fn color_tween() {
+        let mut tween = Tween::new(Color::black(), Color::white(), 2.0);
+        assert_eq!(tween.advance(1.0), Color::gray());
+        assert!(!tween.is_finished());
+        assert_eq!(tween.advance(5.0), Color::white());
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn animating_an_object() {
+        let mut animation = Animation::new()
+            .with_position(
+                Track::new()
+                    .with_keyframe(0.0, vec3(0.0, 0.0, 0.0), Easing::Linear)
+                    .with_keyframe(2.0, vec3(0.0, 4.0, 0.0), Easing::Linear)
+            )
+            .with_loop_mode(LoopMode::PingPong);
+        let mut cube = Object::new_cube(Color::red());
+        animation.advance(3.0).apply_to_object(&mut cube);
+        assert!(cube.transform().w_axis.truncate().abs_diff_eq(vec3(0.0, 2.0, 0.0), 1.0e-5));
+    }
+}
@@ -0,0 +1,141 @@
+use glam::{vec2, Mat4, Vec2, Vec3, Vec4Swizzles};
+
+use crate::{color::Color, graphics::{Rect2d, Vertex}, renderer::Mode, texture::Texture};
+
+/// Right and up directions of the screen in world space, from the camera world transform
+pub fn camera_basis(camera: &Mat4, mode: Mode) -> (Vec3, Vec3) {
+    let right = camera.x_axis.xyz().normalize_or_zero();
+    let up = camera.y_axis.xyz().normalize_or_zero();
+    match mode {
+        Mode::Mode3d => (right, up),
+        // the 2D image goes down
+        Mode::Mode2d => (right, -up),
+    }
+}
+
+/// Quad always turned toward the camera, drawn with `Graphics::draw_billboard`.
+/// Its size is in world units in 3D and in pixels in 2D.
+#[derive(Debug, Clone)]
+pub struct Billboard {
+    pub position: Vec3,
+    pub size: Vec2,
+    pub color: Color,
+    pub texture: Option<Texture>,
+    /// Part of the texture shown, in texture coordinates
+    pub region: Rect2d,
+    /// Keeps the quad upright along this axis instead of fully facing the camera
+    pub axis: Option<Vec3>,
+}
+
+impl Billboard {
+    pub fn new(position: Vec3, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            color: Color::white(),
+            texture: None,
+            region: Rect2d { position: Vec2::ZERO, size: Vec2::ONE },
+            axis: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Shows a part of the texture, e.g. a frame of a sprite sheet
+    pub fn with_region(mut self, region: Rect2d) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Only turns around `axis`, like trees and characters turning around the vertical
+    pub fn with_axis(mut self, axis: Vec3) -> Self {
+        self.axis = Some(axis.normalize_or_zero());
+        self
+    }
+
+    pub fn set_position(&mut self, position: Vec3) -> &mut Self {
+        self.position = position;
+        self
+    }
+
+    /// Corners from the top left, in reading order
+    pub fn corners(&self, camera: &Mat4, mode: Mode) -> [Vec3; 4] {
+        let (mut right, mut up) = camera_basis(camera, mode);
+        if let (Some(axis), Mode::Mode3d) = (self.axis, mode) {
+            let locked_right = axis.cross(camera.z_axis.xyz()).normalize_or_zero();
+            // looking along the axis, there is no better choice than the camera
+            if locked_right != Vec3::ZERO {
+                right = locked_right;
+                up = axis;
+            }
+        }
+
+        let right = right * self.size.x * 0.5;
+        let up = up * self.size.y * 0.5;
+        [
+            self.position - right + up,
+            self.position + right + up,
+            self.position - right - up,
+            self.position + right - up,
+        ]
+    }
+
+    pub fn vertices(&self, camera: &Mat4, mode: Mode) -> Vec<Vertex> {
+        let min = self.region.position;
+        let max = self.region.position + self.region.size;
+        let uvs = [min, vec2(max.x, min.y), vec2(min.x, max.y), max];
+        self.corners(camera, mode)
+            .iter()
+            .zip(uvs)
+            .map(|(corner, uv)| Vertex {
+                position: corner.to_array(),
+                color: self.color.as_array(),
+                normal: [0.0, 0.0, 0.0],
+                uv: uv.to_array(),
+            })
+            .collect()
+    }
+
+    pub fn indices() -> Vec<i32> {
+        vec![0, 1, 2, 1, 3, 2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec3, Quat};
+
+    use super::*;
+
+    #[test]
+    fn faces_the_camera_or_stays_upright() {
+        // looking down at 45° from the +x side
+        let camera = Mat4::from_rotation_translation(
+            Quat::from_rotation_y(FRAC_PI_2) * Quat::from_rotation_x(-FRAC_PI_2 / 2.0),
+            vec3(5.0, 5.0, 0.0),
+        );
+        let forward = -camera.z_axis.xyz();
+
+        let billboard = Billboard::new(Vec3::ZERO, vec2(2.0, 1.0));
+        let [top_left, top_right, bottom_left, _] = billboard.corners(&camera, Mode::Mode3d);
+        assert!((top_right - top_left).dot(forward).abs() < 1.0e-5);
+        assert!((top_left - bottom_left).dot(forward).abs() < 1.0e-5);
+        assert!(((top_right - top_left).length() - 2.0).abs() < 1.0e-5);
+
+        let upright = billboard.with_axis(Vec3::Y);
+        let [top_left, top_right, bottom_left, _] = upright.corners(&camera, Mode::Mode3d);
+        assert!((top_left - bottom_left - Vec3::Y).length() < 1.0e-5);
+        // turned toward the camera around the vertical
+        assert!((top_right - top_left).normalize().abs_diff_eq(vec3(0.0, 0.0, -1.0), 1.0e-5));
+    }
+}
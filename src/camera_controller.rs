@@ -0,0 +1,464 @@
+use std::f32::consts::PI;
+
+use glam::{vec3, EulerRot, Mat4, Quat, Vec3};
+use miniquad::{KeyCode, MouseButton};
+
+use crate::{graphics::Camera3d, inputs::Inputs};
+
+const MAX_PITCH: f32 = PI / 2.0 - 0.01;
+
+/// Drives a `Camera3d` from the inputs
+pub trait CameraController {
+    fn update(&mut self, camera: &mut Camera3d, inputs: &Inputs, dt: f32);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MoveKeys {
+    pub forward: KeyCode,
+    pub backward: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub boost: KeyCode,
+}
+
+impl Default for MoveKeys {
+    fn default() -> Self {
+        Self {
+            forward: KeyCode::W,
+            backward: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+            up: KeyCode::E,
+            down: KeyCode::Q,
+            boost: KeyCode::LeftShift,
+        }
+    }
+}
+
+impl MoveKeys {
+    /// Direction requested by the pressed keys in camera space (x right, y up, z backward).
+    /// Several keys can be held at once and diagonals are not faster.
+    pub fn direction(&self, inputs: &Inputs) -> Vec3 {
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            inputs.key(positive) as i32 as f32 - inputs.key(negative) as i32 as f32
+        };
+
+        vec3(
+            axis(self.right, self.left),
+            axis(self.up, self.down),
+            axis(self.backward, self.forward),
+        )
+        .normalize_or_zero()
+    }
+}
+
+/// Rotation of a camera looking down -z, turned by `yaw` around y then by `pitch` around x
+pub fn look_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0)
+}
+
+fn yaw_pitch(camera: &Camera3d) -> (f32, f32) {
+    let forward = -camera.transform().z_axis.truncate().normalize_or_zero();
+    let yaw = (-forward.x).atan2(-forward.z);
+    let pitch = forward.y.clamp(-1.0, 1.0).asin();
+    (yaw, pitch)
+}
+
+fn smoothing_factor(smoothing: f32, dt: f32) -> f32 {
+    if smoothing <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-smoothing * dt).exp()
+    }
+}
+
+/// Walks on the horizontal plane and looks around with the mouse
+pub struct FirstPersonController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub move_speed: f32,
+    pub look_sensitivity: f32,
+    pub keys: MoveKeys,
+}
+
+impl Default for FirstPersonController {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            move_speed: 5.0,
+            look_sensitivity: 0.003,
+            keys: Default::default(),
+        }
+    }
+}
+
+impl FirstPersonController {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts from the current orientation of the camera
+    pub fn from_camera(camera: &Camera3d) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera);
+        Self {
+            yaw,
+            pitch,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_move_speed(mut self, move_speed: f32) -> Self {
+        self.move_speed = move_speed;
+        self
+    }
+
+    pub fn with_look_sensitivity(mut self, look_sensitivity: f32) -> Self {
+        self.look_sensitivity = look_sensitivity;
+        self
+    }
+
+    pub fn with_keys(mut self, keys: MoveKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    pub fn rotation(&self) -> Quat {
+        look_rotation(self.yaw, self.pitch)
+    }
+
+    /// Horizontal displacement requested by the inputs for this frame
+    pub fn movement(&self, inputs: &Inputs, dt: f32) -> Vec3 {
+        let mut direction = self.keys.direction(inputs);
+        direction.y = 0.0;
+        Quat::from_rotation_y(self.yaw) * direction * self.move_speed * dt
+    }
+
+    pub fn look(&mut self, inputs: &Inputs) {
+        let delta = inputs.mouse_delta() * self.look_sensitivity;
+        self.yaw -= delta.x;
+        self.pitch = (self.pitch - delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn update(&mut self, camera: &mut Camera3d, inputs: &Inputs, dt: f32) {
+        self.look(inputs);
+        let position = camera.position() + self.movement(inputs, dt);
+        camera.set_transform(&Mat4::from_rotation_translation(self.rotation(), position));
+    }
+}
+
+/// Flies in the direction the camera is looking at
+pub struct FreeFlyController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub move_speed: f32,
+    pub boost_factor: f32,
+    pub look_sensitivity: f32,
+    pub keys: MoveKeys,
+}
+
+impl Default for FreeFlyController {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            move_speed: 5.0,
+            boost_factor: 3.0,
+            look_sensitivity: 0.003,
+            keys: Default::default(),
+        }
+    }
+}
+
+impl FreeFlyController {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts from the current orientation of the camera
+    pub fn from_camera(camera: &Camera3d) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera);
+        Self {
+            yaw,
+            pitch,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_move_speed(mut self, move_speed: f32) -> Self {
+        self.move_speed = move_speed;
+        self
+    }
+
+    pub fn with_boost_factor(mut self, boost_factor: f32) -> Self {
+        self.boost_factor = boost_factor;
+        self
+    }
+
+    pub fn with_look_sensitivity(mut self, look_sensitivity: f32) -> Self {
+        self.look_sensitivity = look_sensitivity;
+        self
+    }
+
+    pub fn with_keys(mut self, keys: MoveKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+}
+
+impl CameraController for FreeFlyController {
+    fn update(&mut self, camera: &mut Camera3d, inputs: &Inputs, dt: f32) {
+        let delta = inputs.mouse_delta() * self.look_sensitivity;
+        self.yaw -= delta.x;
+        self.pitch = (self.pitch - delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let rotation = look_rotation(self.yaw, self.pitch);
+        let speed = if inputs.key(self.keys.boost) {
+            self.move_speed * self.boost_factor
+        } else {
+            self.move_speed
+        };
+        let position = camera.position() + rotation * self.keys.direction(inputs) * speed * dt;
+        camera.set_transform(&Mat4::from_rotation_translation(rotation, position));
+    }
+}
+
+/// Turns around a target point and zooms with the mouse wheel
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub orbit_sensitivity: f32,
+    pub zoom_speed: f32,
+    /// Button to hold while dragging to orbit, always orbits when `None`
+    pub orbit_button: Option<MouseButton>,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 5.0,
+            min_distance: 1.0,
+            max_distance: 50.0,
+            yaw: 0.0,
+            pitch: -0.3,
+            orbit_sensitivity: 0.005,
+            zoom_speed: 0.5,
+            orbit_button: Some(MouseButton::Left),
+        }
+    }
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3) -> Self {
+        Self {
+            target,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_distance(mut self, distance: f32) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_distance_range(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_angles(mut self, yaw: f32, pitch: f32) -> Self {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_orbit_sensitivity(mut self, orbit_sensitivity: f32) -> Self {
+        self.orbit_sensitivity = orbit_sensitivity;
+        self
+    }
+
+    pub fn with_zoom_speed(mut self, zoom_speed: f32) -> Self {
+        self.zoom_speed = zoom_speed;
+        self
+    }
+
+    pub fn with_orbit_button(mut self, orbit_button: Option<MouseButton>) -> Self {
+        self.orbit_button = orbit_button;
+        self
+    }
+
+    pub fn set_target(&mut self, target: Vec3) -> &mut Self {
+        self.target = target;
+        self
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera3d, inputs: &Inputs, _dt: f32) {
+        if self.orbit_button.is_none_or(|button| inputs.mouse_button(button)) {
+            let delta = inputs.mouse_delta() * self.orbit_sensitivity;
+            self.yaw -= delta.x;
+            self.pitch = (self.pitch - delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        self.distance = (self.distance - inputs.mouse_wheel().y * self.zoom_speed)
+            .clamp(self.min_distance, self.max_distance);
+
+        let rotation = look_rotation(self.yaw, self.pitch);
+        let position = self.target + rotation * vec3(0.0, 0.0, self.distance);
+        camera.set_transform(&Mat4::from_rotation_translation(rotation, position));
+    }
+}
+
+/// Casts a ray from `origin` along the normalized `direction` and returns the distance
+/// to the first obstacle closer than `max_distance`
+pub type RayCast = Box<dyn Fn(Vec3, Vec3, f32) -> Option<f32>>;
+
+/// Third person camera trailing behind a moving target
+pub struct FollowController {
+    pub target: Vec3,
+    pub target_yaw: f32,
+    pub distance: f32,
+    pub height: f32,
+    pub look_height: f32,
+    /// How fast the camera catches up with its ideal position, 0 disables smoothing
+    pub smoothing: f32,
+    /// Distance kept between the camera and the obstacles
+    pub collision_margin: f32,
+    collision: Option<RayCast>,
+    position: Option<Vec3>,
+}
+
+impl Default for FollowController {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            target_yaw: 0.0,
+            distance: 4.0,
+            height: 1.5,
+            look_height: 0.5,
+            smoothing: 8.0,
+            collision_margin: 0.2,
+            collision: None,
+            position: None,
+        }
+    }
+}
+
+impl FollowController {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_offset(mut self, distance: f32, height: f32) -> Self {
+        self.distance = distance;
+        self.height = height;
+        self
+    }
+
+    pub fn with_look_height(mut self, look_height: f32) -> Self {
+        self.look_height = look_height;
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Pulls the camera in front of the obstacles found by `ray_cast`
+    pub fn with_collision(mut self, ray_cast: impl Fn(Vec3, Vec3, f32) -> Option<f32> + 'static) -> Self {
+        self.collision = Some(Box::new(ray_cast));
+        self
+    }
+
+    /// Sets the followed position and the direction it is facing
+    pub fn set_target(&mut self, target: Vec3, yaw: f32) -> &mut Self {
+        self.target = target;
+        self.target_yaw = yaw;
+        self
+    }
+
+    fn ideal_position(&self, look_at: Vec3) -> Vec3 {
+        let behind = Quat::from_rotation_y(self.target_yaw) * vec3(0.0, 0.0, self.distance);
+        let ideal = self.target + behind + vec3(0.0, self.height, 0.0);
+
+        let Some(ray_cast) = self.collision.as_ref() else {
+            return ideal;
+        };
+
+        let offset = ideal - look_at;
+        let length = offset.length();
+        if length <= f32::EPSILON {
+            return ideal;
+        }
+        let direction = offset / length;
+        match ray_cast(look_at, direction, length) {
+            Some(hit) => look_at + direction * (hit - self.collision_margin).max(0.0),
+            None => ideal,
+        }
+    }
+}
+
+impl CameraController for FollowController {
+    fn update(&mut self, camera: &mut Camera3d, _inputs: &Inputs, dt: f32) {
+        let look_at = self.target + vec3(0.0, self.look_height, 0.0);
+        let ideal = self.ideal_position(look_at);
+
+        let position = match self.position {
+            Some(position) => position.lerp(ideal, smoothing_factor(self.smoothing, dt)),
+            None => ideal,
+        };
+        self.position = Some(position);
+
+        camera
+            .set_position(position)
+            .look_at(look_at, Vec3::Y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagonal_moves_are_not_faster() {
+        let mut inputs = Inputs::new();
+        inputs.key_down_event(KeyCode::W);
+        inputs.key_down_event(KeyCode::D);
+        let direction = MoveKeys::default().direction(&inputs);
+        assert!((direction.length() - 1.0).abs() < 1.0e-5);
+        assert!(direction.x > 0.0 && direction.z < 0.0);
+    }
+
+    #[test]
+    fn first_person_starts_from_the_camera_orientation() {
+        let mut camera = Camera3d::new();
+        camera.set_transform(&Mat4::from_quat(look_rotation(0.7, -0.2)));
+        let controller = FirstPersonController::from_camera(&camera);
+        assert!((controller.yaw - 0.7).abs() < 1.0e-4);
+        assert!((controller.pitch + 0.2).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn follow_camera_stays_in_front_of_obstacles() {
+        let mut camera = Camera3d::new();
+        let mut controller = FollowController::new()
+            .with_offset(4.0, 0.0)
+            .with_look_height(0.0)
+            .with_smoothing(0.0)
+            .with_collision(|_, _, _| Some(2.0));
+        controller.update(&mut camera, &Inputs::new(), 0.016);
+        assert!(camera.position().abs_diff_eq(vec3(0.0, 0.0, 1.8), 1.0e-5));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{collections::{HashMap, VecDeque}, fmt, fs::File, io::{BufWriter, Write}, path::{Path, PathBuf}, sync::Arc, thread::{self, JoinHandle}};
+
+use glam::UVec2;
+
+use crate::{graphics::Graphics, texture::Texture};
+
+/// Time between two frames of a GIF, in seconds, GIF delays are counted in hundredths of a second
+const GIF_FRAME_INTERVAL: f64 = 0.04;
+
+/// Quality of the palettes computed for frames with more than 256 colors, from 1 (best) to 30 (fastest)
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+    /// No frame was recorded yet
+    Empty,
+    /// The thread saving a GIF panicked
+    Panicked,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(err) => write!(f, "io error: {}", err),
+            CaptureError::Png(err) => write!(f, "cannot encode png: {}", err),
+            CaptureError::Gif(err) => write!(f, "cannot encode gif: {}", err),
+            CaptureError::Empty => write!(f, "no frame to save"),
+            CaptureError::Panicked => write!(f, "the saving thread panicked"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(err: std::io::Error) -> Self {
+        CaptureError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for CaptureError {
+    fn from(err: png::EncodingError) -> Self {
+        CaptureError::Png(err)
+    }
+}
+
+impl From<gif::EncodingError> for CaptureError {
+    fn from(err: gif::EncodingError) -> Self {
+        CaptureError::Gif(err)
+    }
+}
+
+/// Screenshot requested during a frame, saved by the renderer once the image is drawn
+pub struct Screenshot {
+    pub path: PathBuf,
+    pub scale: u32,
+}
+
+/// Nearest neighbour upscale by an integer factor, the pixels stay sharp
+pub fn upscale(image: &Texture, scale: u32) -> Texture {
+    let scale = scale.max(1);
+    if scale == 1 {
+        return image.clone();
+    }
+    let row_len = (image.width() * 4) as usize;
+    let mut pixels = Vec::with_capacity(image.pixels().len() * (scale * scale) as usize);
+    for row in image.pixels().chunks_exact(row_len) {
+        let start = pixels.len();
+        for pixel in row.chunks_exact(4) {
+            for _ in 0..scale {
+                pixels.extend_from_slice(pixel);
+            }
+        }
+        let end = pixels.len();
+        for _ in 1..scale {
+            pixels.extend_from_within(start..end);
+        }
+    }
+    Texture::new(image.width() * scale, image.height() * scale, pixels)
+}
+
+/// Writes the image as an RGBA PNG, upscaled by `scale`.
+/// Works on any texture, without a window.
+pub fn encode_png(image: &Texture, scale: u32, writer: impl Write) -> Result<(), CaptureError> {
+    let image = upscale(image, scale);
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(image.pixels())?;
+    Ok(())
+}
+
+pub fn save_png(image: &Texture, scale: u32, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+    encode_png(image, scale, BufWriter::new(File::create(path)?))
+}
+
+/// Pixels shared with the thread encoding them, see `GifRecorder::save_in_background`
+#[derive(Clone)]
+struct GifFrame {
+    size: UVec2,
+    pixels: Arc<[u8]>,
+    /// Time from `miniquad::date::now` when the frame was drawn
+    time: f64,
+}
+
+/// Keeps the frames of the last seconds, to be saved as a looping GIF.
+/// A frame of the 320x200 image takes 256 KB, about 32 MB for 5 seconds.
+pub struct GifRecorder {
+    duration: f64,
+    frames: VecDeque<GifFrame>,
+}
+
+impl GifRecorder {
+    /// Records the last `duration` seconds
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration: duration as f64,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// The recorder only keeps one frame per `GIF_FRAME_INTERVAL`, the others don't need to be read back
+    pub fn wants_frame(&self, time: f64) -> bool {
+        self.frames.back().is_none_or(|last| time - last.time >= GIF_FRAME_INTERVAL)
+    }
+
+    /// Adds a frame and drops the ones older than the duration.
+    /// The recording restarts when the size of the image changes.
+    pub fn push(&mut self, image: &Texture, time: f64) {
+        if self.frames.back().is_some_and(|last| last.size != image.size()) {
+            self.frames.clear();
+        }
+        self.frames.push_back(GifFrame {
+            size: image.size(),
+            pixels: Arc::from(image.pixels()),
+            time,
+        });
+        while self.frames.front().is_some_and(|first| time - first.time > self.duration) {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Writes the recorded frames as a looping GIF.
+    /// The frames share a single exact palette when they use at most 256 colors,
+    /// each one gets its own quantized palette otherwise, which takes seconds.
+    pub fn encode(&self, writer: impl Write) -> Result<(), CaptureError> {
+        encode_gif(self.frames.iter(), writer)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CaptureError> {
+        self.encode(BufWriter::new(File::create(path)?))
+    }
+
+    /// Saves the frames recorded so far from another thread, the recording goes on meanwhile
+    pub fn save_in_background(&self, path: impl AsRef<Path>) -> JoinHandle<Result<(), CaptureError>> {
+        let frames: Vec<GifFrame> = self.frames.iter().cloned().collect();
+        let path = path.as_ref().to_path_buf();
+        thread::spawn(move || encode_gif(frames.iter(), BufWriter::new(File::create(path)?)))
+    }
+}
+
+fn encode_gif<'a>(frames: impl ExactSizeIterator<Item = &'a GifFrame> + Clone, writer: impl Write) -> Result<(), CaptureError> {
+    let first = frames.clone().next().ok_or(CaptureError::Empty)?;
+    let (width, height) = (first.size.x as u16, first.size.y as u16);
+    let palette = Palette::from_frames(frames.clone());
+
+    let mut encoder = gif::Encoder::new(writer, width, height, palette.as_ref().map_or(&[], |palette| &palette.colors))?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    let start = first.time;
+    let mut delay = (GIF_FRAME_INTERVAL * 100.0).round();
+    let mut next_frames = frames.clone().skip(1);
+    for frame in frames {
+        let mut gif_frame = match &palette {
+            Some(palette) => gif::Frame::from_indexed_pixels(width, height, palette.indices(&frame.pixels), None),
+            None => gif::Frame::from_rgba_speed(width, height, &mut frame.pixels.to_vec(), GIF_QUANTIZE_SPEED),
+        };
+        // rounded from the start of the recording so that the errors don't add up,
+        // the last frame lasts as long as the previous one
+        if let Some(next) = next_frames.next() {
+            delay = ((next.time - start) * 100.0).round() - ((frame.time - start) * 100.0).round();
+        }
+        gif_frame.delay = delay.max(1.0) as u16;
+        encoder.write_frame(&gif_frame)?;
+    }
+    Ok(())
+}
+
+/// Colors shared by all the frames of a GIF
+struct Palette {
+    /// RGB triplets
+    colors: Vec<u8>,
+    indices: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    /// `None` when the images use more than 256 colors, the alpha is ignored
+    fn from_frames<'a>(frames: impl Iterator<Item = &'a GifFrame>) -> Option<Self> {
+        let mut palette = Palette {
+            colors: Vec::new(),
+            indices: HashMap::new(),
+        };
+        for frame in frames {
+            for pixel in frame.pixels.chunks_exact(4) {
+                let color = [pixel[0], pixel[1], pixel[2]];
+                if !palette.indices.contains_key(&color) {
+                    let index = u8::try_from(palette.indices.len()).ok()?;
+                    palette.indices.insert(color, index);
+                    palette.colors.extend_from_slice(&color);
+                }
+            }
+        }
+        Some(palette)
+    }
+
+    fn indices(&self, pixels: &[u8]) -> Vec<u8> {
+        pixels
+            .chunks_exact(4)
+            .map(|pixel| self.indices[&[pixel[0], pixel[1], pixel[2]]])
+            .collect()
+    }
+}
+
+/// Name of a new capture file in the working directory
+pub fn capture_path(extension: &str) -> PathBuf {
+    PathBuf::from(format!("polytron-{}.{}", (miniquad::date::now() * 1000.0) as u64, extension))
+}
+
+/// Captures of the image, as it is drawn before being scaled to the window.
+/// The image is read back from the GPU, so capturing needs the console window.
+/// Without one, textures can still be saved with `save_png` or recorded with `GifRecorder::push`.
+impl Graphics<'_> {
+    /// Saves the current frame as a PNG once it is drawn, upscaled by `scale`
+    pub fn save_screenshot(&mut self, path: impl AsRef<Path>, scale: u32) -> &mut Self {
+        self.data.screenshots.push(Screenshot {
+            path: path.as_ref().to_path_buf(),
+            scale,
+        });
+        self
+    }
+
+    /// Keeps the last `duration` seconds for `save_gif`, stops recording when `None`.
+    /// While recording, the image is read back from the GPU up to 25 times per second.
+    pub fn record_gif(&mut self, duration: Option<f32>) -> &mut Self {
+        self.data.recorder = duration.map(GifRecorder::new);
+        self
+    }
+
+    /// Saves the recorded frames, up to the previous one, from another thread.
+    /// `None` when nothing is being recorded.
+    /// Unlike screenshots, the errors are only returned by the handle.
+    pub fn save_gif(&self, path: impl AsRef<Path>) -> Option<JoinHandle<Result<(), CaptureError>>> {
+        self.data.recorder.as_ref().map(|recorder| recorder.save_in_background(path))
+    }
+
+    /// Captures that couldn't be saved, with their path, kept until taken
+    pub fn take_capture_errors(&mut self) -> Vec<(PathBuf, CaptureError)> {
+        std::mem::take(&mut self.data.capture_errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+
+    use super::*;
+
+    #[test]
+    fn png_is_upscaled() {
+        let image = Texture::from_fn(2, 1, |x, _| if x == 0 { Color::red() } else { Color::blue() });
+        let mut bytes = Vec::new();
+        encode_png(&image, 3, &mut bytes).unwrap();
+
+        let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (6, 3));
+        assert_eq!(pixels, upscale(&image, 3).pixels());
+        assert_eq!(&pixels[8..12], &[255, 0, 0, 255]);
+        assert_eq!(&pixels[12..16], &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn gif_keeps_the_last_seconds() {
+        let mut recorder = GifRecorder::new(1.0);
+        for i in 0..100 {
+            let time = i as f64 * 0.03;
+            if recorder.wants_frame(time) {
+                let shade = i as f32 / 100.0;
+                recorder.push(&Texture::from_color(4, 4, Color::new(shade, shade, shade, 1.0)), time);
+            }
+        }
+        // every other frame, from 1.98 to 2.94 seconds
+        assert_eq!(recorder.frame_count(), 17);
+
+        let mut bytes = Vec::new();
+        recorder.encode(&mut bytes).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(bytes.as_slice()).unwrap();
+        assert!(decoder.global_palette().is_some());
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            // the gray levels fit in the shared palette
+            assert!(frame.palette.is_none());
+            assert_eq!(frame.delay, 6);
+            frames += 1;
+        }
+        assert_eq!(frames, 17);
+
+        let path = std::env::temp_dir().join(format!("polytron-test-{}.gif", std::process::id()));
+        recorder.save_in_background(&path).join().unwrap().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        std::fs::remove_file(&path).unwrap();
+
+        recorder.push(&Texture::from_color(2, 2, Color::white()), 2.0);
+        assert_eq!(recorder.frame_count(), 1);
+    }
+}
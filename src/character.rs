@@ -0,0 +1,317 @@
+use std::f32::consts::PI;
+
+use glam::{vec3, Mat4, Vec3};
+
+use crate::{collision::{Collider, ColliderId, CollisionWorld, Contact}, graphics::Camera3d, object::Object};
+
+const RESOLVE_ITERATIONS: usize = 4;
+
+fn horizontal(v: Vec3) -> Vec3 {
+    vec3(v.x, 0.0, v.z)
+}
+
+/// Walking capsule moved by code rather than by the physics, it slides along walls,
+/// climbs steps and gentle slopes and falls with gravity
+pub struct CharacterController {
+    pub radius: f32,
+    pub height: f32,
+    pub eye_height: f32,
+    /// Steepest walkable slope in radians
+    pub max_slope: f32,
+    pub step_height: f32,
+    pub gravity: f32,
+    pub jump_speed: f32,
+    /// Position of the feet
+    position: Vec3,
+    vertical_speed: f32,
+    grounded: bool,
+    ground_normal: Vec3,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            radius: 0.3,
+            height: 1.8,
+            eye_height: 1.6,
+            max_slope: PI / 4.0,
+            step_height: 0.3,
+            gravity: 9.81,
+            jump_speed: 5.0,
+            position: Vec3::ZERO,
+            vertical_speed: 0.0,
+            grounded: false,
+            ground_normal: Vec3::Y,
+        }
+    }
+}
+
+impl CharacterController {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// Starts with the eyes at the camera position
+    pub fn from_camera(camera: &Camera3d) -> Self {
+        let character = Self::default();
+        Self::new(camera.position() - Vec3::Y * character.eye_height)
+    }
+
+    pub fn with_size(mut self, radius: f32, height: f32, eye_height: f32) -> Self {
+        self.radius = radius;
+        self.height = height.max(radius * 2.0);
+        self.eye_height = eye_height;
+        self
+    }
+
+    pub fn with_max_slope(mut self, max_slope: f32) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    pub fn with_step_height(mut self, step_height: f32) -> Self {
+        self.step_height = step_height;
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_jump_speed(mut self, jump_speed: f32) -> Self {
+        self.jump_speed = jump_speed;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.position + Vec3::Y * self.eye_height
+    }
+
+    /// Teleports the character without checking collisions
+    pub fn set_position(&mut self, position: Vec3) -> &mut Self {
+        self.position = position;
+        self.vertical_speed = 0.0;
+        self
+    }
+
+    pub fn vertical_speed(&self) -> f32 {
+        self.vertical_speed
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn ground_normal(&self) -> Vec3 {
+        self.ground_normal
+    }
+
+    pub fn collider(&self) -> Collider {
+        Collider::capsule(self.radius, self.height * 0.5 - self.radius)
+    }
+
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_translation(self.position + Vec3::Y * self.height * 0.5)
+    }
+
+    /// Jumps when standing on the ground, returns whether it did
+    pub fn jump(&mut self) -> bool {
+        if !self.grounded {
+            return false;
+        }
+        self.vertical_speed = self.jump_speed;
+        self.grounded = false;
+        true
+    }
+
+    /// Walks by the horizontal part of `movement` then applies gravity, resolving
+    /// against the solid colliders of the world
+    pub fn update(&mut self, movement: Vec3, world: &CollisionWorld, dt: f32) {
+        let was_grounded = self.grounded;
+        let jumping = self.vertical_speed > 0.0;
+        self.vertical_speed -= self.gravity * dt;
+
+        let movement = vec3(movement.x, 0.0, movement.z);
+        let start = self.position;
+        self.slide(movement, world);
+
+        let walked = horizontal(self.position - start).length();
+        if was_grounded && self.step_height > 0.0 && walked + 1.0e-4 < movement.length() {
+            self.step_up(start, movement, walked, world);
+        }
+
+        self.grounded = false;
+        self.slide(Vec3::Y * self.vertical_speed * dt, world);
+
+        // stay on the ground when walking down slopes and steps
+        if was_grounded && !jumping && !self.grounded {
+            let before = self.position;
+            self.slide(-Vec3::Y * self.step_height, world);
+            if !self.grounded {
+                self.position = before;
+            }
+        }
+
+        if self.grounded {
+            self.vertical_speed = self.vertical_speed.max(0.0);
+        } else {
+            self.ground_normal = Vec3::Y;
+        }
+    }
+
+    /// Retries a blocked move from `step_height` higher, kept if it goes further
+    fn step_up(&mut self, start: Vec3, movement: Vec3, walked: f32, world: &CollisionWorld) {
+        let blocked = self.position;
+        let grounded = self.grounded;
+
+        self.position = start;
+        self.slide(Vec3::Y * self.step_height, world);
+        self.slide(movement, world);
+        self.grounded = false;
+        self.slide(-Vec3::Y * self.step_height, world);
+
+        let stepped = horizontal(self.position - start).length();
+        if !self.grounded || stepped <= walked + 1.0e-4 {
+            self.position = blocked;
+            self.grounded = grounded;
+        }
+    }
+
+    /// The rounded bottom of the capsule sees step edges as steep slopes, a small probe
+    /// just past the contact finds the normal of the surface actually touched
+    fn edge_ground(&self, id: ColliderId, contact: &Contact, world: &CollisionWorld) -> Option<Vec3> {
+        if contact.point.y - self.position.y > self.step_height {
+            return None;
+        }
+        let probe = Collider::aabb(Vec3::splat(0.02));
+        let position = contact.point + horizontal(contact.normal).normalize_or_zero() * 0.05;
+        let min_ground_y = self.max_slope.cos();
+        world
+            .overlaps(&probe, &Mat4::from_translation(position))
+            .iter()
+            .filter(|(other, _)| *other == id)
+            .map(|(_, contact)| -contact.normal)
+            .find(|normal| normal.y >= min_ground_y)
+    }
+
+    /// Moves in steps smaller than the radius so thin walls are not crossed
+    fn slide(&mut self, motion: Vec3, world: &CollisionWorld) {
+        let steps = (motion.length() / (self.radius * 0.5)).ceil().max(1.0);
+        let step = motion / steps;
+        for _ in 0..steps as usize {
+            self.position += step;
+            self.resolve(world);
+        }
+    }
+
+    fn resolve(&mut self, world: &CollisionWorld) {
+        let collider = self.collider();
+        let min_ground_y = self.max_slope.cos();
+
+        for _ in 0..RESOLVE_ITERATIONS {
+            let contacts = world.overlaps(&collider, &self.transform());
+            if contacts.is_empty() {
+                return;
+            }
+
+            for (id, contact) in contacts {
+                let push = -contact.normal;
+                let ground = if push.y >= min_ground_y {
+                    Some(push)
+                } else if push.y > 0.0 {
+                    self.edge_ground(id, &contact, world)
+                } else {
+                    None
+                };
+
+                if let Some(ground) = ground {
+                    // walkable, straight up so the character doesn't slide down the slope
+                    self.position.y += (contact.depth / push.y).min(self.step_height);
+                    self.grounded = true;
+                    self.ground_normal = ground;
+                } else if push.y > 0.0 {
+                    // too steep, only pushed sideways so it can't be climbed
+                    let sideways = horizontal(push);
+                    self.position += sideways * (contact.depth / sideways.length_squared());
+                } else {
+                    self.position += push * contact.depth;
+                    if push.y < -0.5 {
+                        self.vertical_speed = self.vertical_speed.min(0.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Puts the camera at the eyes, keeping its orientation
+    pub fn apply_to_camera(&self, camera: &mut Camera3d) {
+        camera.set_position(self.eye_position());
+    }
+
+    /// Moves the object to the feet, keeping its rotation and scale
+    pub fn apply_to_object(&self, object: &mut Object) {
+        let (scale, rotation, _) = object.transform().to_scale_rotation_translation();
+        object.set_transform(&Mat4::from_scale_rotation_translation(scale, rotation, self.position));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level() -> CollisionWorld {
+        let mut world = CollisionWorld::new();
+        world.add(Collider::aabb(vec3(10.0, 0.5, 10.0)), &Mat4::from_translation(vec3(0.0, -0.5, 0.0)));
+        // step
+        world.add(Collider::aabb(vec3(1.0, 0.1, 10.0)), &Mat4::from_translation(vec3(3.0, 0.1, 0.0)));
+        // wall
+        world.add(Collider::aabb(vec3(1.0, 2.0, 10.0)), &Mat4::from_translation(vec3(-3.0, 2.0, 0.0)));
+        world
+    }
+
+    fn run(character: &mut CharacterController, world: &CollisionWorld, velocity: Vec3, frames: usize) {
+        let dt = 1.0 / 60.0;
+        for _ in 0..frames {
+            character.update(velocity * dt, world, dt);
+        }
+    }
+
+    #[test]
+    fn falls_lands_and_jumps() {
+        let world = level();
+        let mut character = CharacterController::new(vec3(0.0, 2.0, 0.0));
+        run(&mut character, &world, Vec3::ZERO, 120);
+        assert!(character.is_grounded());
+        assert!(character.position().y.abs() < 0.01, "{}", character.position());
+
+        assert!(character.jump());
+        run(&mut character, &world, Vec3::ZERO, 10);
+        assert!(!character.is_grounded());
+        assert!(character.position().y > 0.5);
+        assert!(!character.jump());
+    }
+
+    #[test]
+    fn climbs_steps_and_stops_at_walls() {
+        let world = level();
+        let mut character = CharacterController::new(Vec3::ZERO);
+        run(&mut character, &world, Vec3::ZERO, 2);
+
+        run(&mut character, &world, vec3(3.0, 0.0, 0.0), 60);
+        assert!(character.position().x > 2.5);
+        assert!((character.position().y - 0.2).abs() < 0.02, "{}", character.position());
+
+        run(&mut character, &world, vec3(-3.0, 0.0, 1.0), 120);
+        assert!((character.position().x - (-2.0 + character.radius)).abs() < 0.02, "{}", character.position());
+        assert!(character.position().z > 1.5);
+        assert!(character.position().y.abs() < 0.01);
+    }
+}
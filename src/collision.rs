@@ -0,0 +1,867 @@
+use std::collections::HashSet;
+
+use glam::{Mat4, Vec3};
+
+use crate::{object::Object, picking::local_bounds};
+
+const GJK_ITERATIONS: usize = 64;
+const EPA_ITERATIONS: usize = 64;
+const EPA_TOLERANCE: f32 = 1.0e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.min.cmple(point).all() && point.cmple(self.max).all()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+/// Collision shape, placed in the world by a transform such as `Object::transform`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collider {
+    /// Box that stays axis aligned, the rotation of its transform is ignored
+    Aabb { half_extents: Vec3 },
+    Sphere { radius: f32 },
+    /// Capsule along the local y axis, `half_height` excludes the caps
+    Capsule { radius: f32, half_height: f32 },
+    /// Convex hull of the points, they don't need to be the hull vertices only
+    ConvexHull { points: Vec<Vec3> },
+}
+
+impl Collider {
+    pub fn aabb(half_extents: Vec3) -> Self {
+        Self::Aabb { half_extents }
+    }
+
+    pub fn sphere(radius: f32) -> Self {
+        Self::Sphere { radius }
+    }
+
+    pub fn capsule(radius: f32, half_height: f32) -> Self {
+        Self::Capsule { radius, half_height }
+    }
+
+    pub fn convex_hull(points: Vec<Vec3>) -> Self {
+        Self::ConvexHull { points }
+    }
+
+    /// Convex hull of the object vertices, to be used with the object transform
+    pub fn from_object(object: &Object) -> Self {
+        Self::ConvexHull {
+            points: object.vertices().iter().map(|v| Vec3::from(v.position)).collect(),
+        }
+    }
+
+    /// Box fitting the object vertices, to be used with the object transform
+    pub fn aabb_from_object(object: &Object) -> Self {
+        let (min, max) = local_bounds(object).unwrap_or_default();
+        Self::Aabb { half_extents: (max - min) * 0.5 }
+    }
+
+    pub fn bounds(&self, transform: &Mat4) -> Aabb {
+        self.place(transform).bounds()
+    }
+
+    fn place(&self, transform: &Mat4) -> Shape {
+        let (scale, _, translation) = transform.to_scale_rotation_translation();
+        let scale = scale.abs();
+        match self {
+            Collider::Aabb { half_extents } => Shape::Aabb(Aabb::from_center(translation, *half_extents * scale)),
+            Collider::Sphere { radius } => Shape::Rounded {
+                start: translation,
+                end: translation,
+                radius: radius * scale.max_element(),
+            },
+            Collider::Capsule { radius, half_height } => {
+                let axis = transform.transform_vector3(Vec3::Y * *half_height);
+                Shape::Rounded {
+                    start: translation - axis,
+                    end: translation + axis,
+                    radius: radius * scale.x.max(scale.z),
+                }
+            }
+            Collider::ConvexHull { points } => Shape::Hull(points.iter().map(|p| transform.transform_point3(*p)).collect()),
+        }
+    }
+}
+
+/// Collider in world space, spheres and capsules are a segment with a radius
+enum Shape {
+    Aabb(Aabb),
+    Rounded { start: Vec3, end: Vec3, radius: f32 },
+    Hull(Vec<Vec3>),
+}
+
+impl Shape {
+    fn support(&self, direction: Vec3) -> Vec3 {
+        match self {
+            Shape::Aabb(aabb) => Vec3::select(direction.cmpge(Vec3::ZERO), aabb.max, aabb.min),
+            Shape::Rounded { start, end, radius } => {
+                let core = if direction.dot(*end - *start) >= 0.0 { *end } else { *start };
+                core + direction.normalize_or_zero() * *radius
+            }
+            Shape::Hull(points) => points
+                .iter()
+                .copied()
+                .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn center(&self) -> Vec3 {
+        match self {
+            Shape::Aabb(aabb) => aabb.center(),
+            Shape::Rounded { start, end, .. } => (*start + *end) * 0.5,
+            Shape::Hull(points) => points.iter().copied().sum::<Vec3>() / points.len().max(1) as f32,
+        }
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self {
+            Shape::Aabb(aabb) => *aabb,
+            Shape::Rounded { start, end, radius } => Aabb::new(start.min(*end) - *radius, start.max(*end) + *radius),
+            Shape::Hull(points) => {
+                let first = points.first().copied().unwrap_or_default();
+                points.iter().fold(Aabb::new(first, first), |aabb, p| Aabb::new(aabb.min.min(*p), aabb.max.max(*p)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// Direction from the first collider to the second one, moving the second one
+    /// by `normal * depth` separates them
+    pub normal: Vec3,
+    pub depth: f32,
+    /// Point halfway between the two surfaces
+    pub point: Vec3,
+}
+
+impl Contact {
+    /// Same contact seen from the second collider
+    pub fn flipped(&self) -> Contact {
+        Contact {
+            normal: -self.normal,
+            ..*self
+        }
+    }
+}
+
+/// Overlap between two colliders placed by their transforms
+pub fn collide(a: &Collider, a_transform: &Mat4, b: &Collider, b_transform: &Mat4) -> Option<Contact> {
+    let a = a.place(a_transform);
+    let b = b.place(b_transform);
+    if !a.bounds().overlaps(&b.bounds()) {
+        return None;
+    }
+
+    match (&a, &b) {
+        (Shape::Aabb(a), Shape::Aabb(b)) => aabb_aabb(a, b),
+        (
+            Shape::Rounded { start: a_start, end: a_end, radius: a_radius },
+            Shape::Rounded { start: b_start, end: b_end, radius: b_radius },
+        ) => {
+            let (a_point, b_point) = closest_points_segments(*a_start, *a_end, *b_start, *b_end);
+            spheres(a_point, *a_radius, b_point, *b_radius)
+        }
+        (Shape::Rounded { start, end, radius }, Shape::Aabb(b)) if start == end => sphere_aabb(*start, *radius, b),
+        (Shape::Aabb(a), Shape::Rounded { start, end, radius }) if start == end => {
+            sphere_aabb(*start, *radius, a).map(|contact| contact.flipped())
+        }
+        _ => gjk(&a, &b).and_then(|simplex| epa(&a, &b, simplex)),
+    }
+}
+
+pub fn intersects(a: &Collider, a_transform: &Mat4, b: &Collider, b_transform: &Mat4) -> bool {
+    collide(a, a_transform, b, b_transform).is_some()
+}
+
+fn aabb_aabb(a: &Aabb, b: &Aabb) -> Option<Contact> {
+    let overlap = a.max.min(b.max) - a.min.max(b.min);
+    if overlap.cmple(Vec3::ZERO).any() {
+        return None;
+    }
+
+    let axis = if overlap.x < overlap.y && overlap.x < overlap.z {
+        0
+    } else if overlap.y < overlap.z {
+        1
+    } else {
+        2
+    };
+    let mut normal = Vec3::ZERO;
+    normal[axis] = if b.center()[axis] >= a.center()[axis] { 1.0 } else { -1.0 };
+
+    Some(Contact {
+        normal,
+        depth: overlap[axis],
+        point: (a.max.min(b.max) + a.min.max(b.min)) * 0.5,
+    })
+}
+
+fn spheres(a_center: Vec3, a_radius: f32, b_center: Vec3, b_radius: f32) -> Option<Contact> {
+    let offset = b_center - a_center;
+    let distance = offset.length();
+    let depth = a_radius + b_radius - distance;
+    if depth <= 0.0 {
+        return None;
+    }
+
+    let normal = if distance > f32::EPSILON { offset / distance } else { Vec3::Y };
+    Some(Contact {
+        normal,
+        depth,
+        point: a_center + normal * (a_radius - depth * 0.5),
+    })
+}
+
+fn sphere_aabb(center: Vec3, radius: f32, aabb: &Aabb) -> Option<Contact> {
+    let closest = center.clamp(aabb.min, aabb.max);
+    if closest != center {
+        let offset = closest - center;
+        let distance = offset.length();
+        if distance >= radius {
+            return None;
+        }
+        let normal = offset / distance;
+        let depth = radius - distance;
+        return Some(Contact {
+            normal,
+            depth,
+            point: closest - normal * depth * 0.5,
+        });
+    }
+
+    // center inside the box, push out through the nearest face
+    let to_min = center - aabb.min;
+    let to_max = aabb.max - center;
+    let distances = to_min.min(to_max);
+    let axis = if distances.x < distances.y && distances.x < distances.z {
+        0
+    } else if distances.y < distances.z {
+        1
+    } else {
+        2
+    };
+    let mut normal = Vec3::ZERO;
+    normal[axis] = if to_min[axis] < to_max[axis] { 1.0 } else { -1.0 };
+    let depth = radius + distances[axis];
+    Some(Contact {
+        normal,
+        depth,
+        point: center + normal * (radius - depth * 0.5),
+    })
+}
+
+/// Closest points between the segments [p1, q1] and [p2, q2], segments can be degenerate
+pub fn closest_points_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom > f32::EPSILON { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Point of the Minkowski difference a - b, remembering the point of a it comes from
+#[derive(Debug, Clone, Copy)]
+struct SupportPoint {
+    point: Vec3,
+    on_a: Vec3,
+}
+
+fn support(a: &Shape, b: &Shape, direction: Vec3) -> SupportPoint {
+    let on_a = a.support(direction);
+    SupportPoint {
+        point: on_a - b.support(-direction),
+        on_a,
+    }
+}
+
+/// Returns a tetrahedron enclosing the origin when the shapes overlap
+fn gjk(a: &Shape, b: &Shape) -> Option<Vec<SupportPoint>> {
+    let mut direction = b.center() - a.center();
+    if direction.length_squared() < f32::EPSILON {
+        direction = Vec3::X;
+    }
+
+    let first = support(a, b, direction);
+    let mut simplex = vec![first];
+    direction = -first.point;
+
+    for _ in 0..GJK_ITERATIONS {
+        if direction.length_squared() < f32::EPSILON {
+            // origin on the simplex, the shapes are only touching
+            return None;
+        }
+
+        let point = support(a, b, direction);
+        if point.point.dot(direction) <= 0.0 {
+            return None;
+        }
+
+        simplex.insert(0, point);
+        if next_simplex(&mut simplex, &mut direction) {
+            return Some(simplex);
+        }
+    }
+
+    None
+}
+
+fn same_direction(direction: Vec3, to: Vec3) -> bool {
+    direction.dot(to) > 0.0
+}
+
+fn next_simplex(simplex: &mut Vec<SupportPoint>, direction: &mut Vec3) -> bool {
+    match simplex.len() {
+        2 => line(simplex, direction),
+        3 => triangle(simplex, direction),
+        4 => tetrahedron(simplex, direction),
+        _ => false,
+    }
+}
+
+fn line(simplex: &mut Vec<SupportPoint>, direction: &mut Vec3) -> bool {
+    let (a, b) = (simplex[0], simplex[1]);
+    let ab = b.point - a.point;
+    let ao = -a.point;
+    if same_direction(ab, ao) {
+        *direction = ab.cross(ao).cross(ab);
+    } else {
+        *simplex = vec![a];
+        *direction = ao;
+    }
+    false
+}
+
+fn triangle(simplex: &mut Vec<SupportPoint>, direction: &mut Vec3) -> bool {
+    let (a, b, c) = (simplex[0], simplex[1], simplex[2]);
+    let ab = b.point - a.point;
+    let ac = c.point - a.point;
+    let ao = -a.point;
+    let abc = ab.cross(ac);
+
+    if same_direction(abc.cross(ac), ao) {
+        if same_direction(ac, ao) {
+            *simplex = vec![a, c];
+            *direction = ac.cross(ao).cross(ac);
+        } else {
+            *simplex = vec![a, b];
+            return line(simplex, direction);
+        }
+    } else if same_direction(ab.cross(abc), ao) {
+        *simplex = vec![a, b];
+        return line(simplex, direction);
+    } else if same_direction(abc, ao) {
+        *direction = abc;
+    } else {
+        *simplex = vec![a, c, b];
+        *direction = -abc;
+    }
+    false
+}
+
+fn tetrahedron(simplex: &mut Vec<SupportPoint>, direction: &mut Vec3) -> bool {
+    let (a, b, c, d) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+    let ab = b.point - a.point;
+    let ac = c.point - a.point;
+    let ad = d.point - a.point;
+    let ao = -a.point;
+
+    if same_direction(ab.cross(ac), ao) {
+        *simplex = vec![a, b, c];
+        return triangle(simplex, direction);
+    }
+    if same_direction(ac.cross(ad), ao) {
+        *simplex = vec![a, c, d];
+        return triangle(simplex, direction);
+    }
+    if same_direction(ad.cross(ab), ao) {
+        *simplex = vec![a, d, b];
+        return triangle(simplex, direction);
+    }
+    true
+}
+
+/// Face of the EPA polytope wound so that its normal points away from the origin
+fn polytope_face(polytope: &[SupportPoint], mut face: [usize; 3]) -> ([usize; 3], Vec3, f32) {
+    let a = polytope[face[0]].point;
+    let normal = (polytope[face[1]].point - a).cross(polytope[face[2]].point - a).normalize_or_zero();
+    let mut distance = normal.dot(a);
+    let mut normal = normal;
+    if distance < 0.0 {
+        face.swap(1, 2);
+        normal = -normal;
+        distance = -distance;
+    }
+    (face, normal, distance)
+}
+
+/// Expanding polytope, finds the penetration from the GJK tetrahedron
+fn epa(a: &Shape, b: &Shape, simplex: Vec<SupportPoint>) -> Option<Contact> {
+    let mut polytope = simplex;
+    let mut faces: Vec<([usize; 3], Vec3, f32)> = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]]
+        .into_iter()
+        .map(|face| polytope_face(&polytope, face))
+        .collect();
+
+    for _ in 0..EPA_ITERATIONS {
+        let (closest, &(_, normal, distance)) =
+            faces.iter().enumerate().min_by(|(_, a), (_, b)| a.2.total_cmp(&b.2))?;
+
+        let point = support(a, b, normal);
+        if point.point.dot(normal) - distance < EPA_TOLERANCE {
+            return Some(epa_contact(&polytope, faces[closest].0, normal, distance));
+        }
+
+        // remove the faces seen from the new point and stitch the hole
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|(face, normal, _)| {
+            if !same_direction(*normal, point.point - polytope[face[0]].point) {
+                return true;
+            }
+            for (start, end) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                match edges.iter().position(|&edge| edge == (end, start)) {
+                    Some(shared) => {
+                        edges.swap_remove(shared);
+                    }
+                    None => edges.push((start, end)),
+                }
+            }
+            false
+        });
+
+        let index = polytope.len();
+        polytope.push(point);
+        faces.extend(edges.into_iter().map(|(start, end)| polytope_face(&polytope, [start, end, index])));
+    }
+
+    let (face, normal, distance) = *faces.iter().min_by(|a, b| a.2.total_cmp(&b.2))?;
+    Some(epa_contact(&polytope, face, normal, distance))
+}
+
+fn epa_contact(polytope: &[SupportPoint], face: [usize; 3], normal: Vec3, depth: f32) -> Contact {
+    let [a, b, c] = face.map(|i| polytope[i]);
+    let barycentric = barycentric(normal * depth, a.point, b.point, c.point);
+    let on_a = a.on_a * barycentric.x + b.on_a * barycentric.y + c.on_a * barycentric.z;
+    Contact {
+        normal,
+        depth,
+        point: on_a - normal * depth * 0.5,
+    }
+}
+
+fn barycentric(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let v0 = b - a;
+    let v1 = c - a;
+    let v2 = p - a;
+    let d00 = v0.dot(v0);
+    let d01 = v0.dot(v1);
+    let d11 = v1.dot(v1);
+    let d20 = v2.dot(v0);
+    let d21 = v2.dot(v1);
+    let denom = d00 * d11 - d01 * d01;
+    if denom.abs() < f32::EPSILON {
+        return Vec3::new(1.0, 0.0, 0.0);
+    }
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    Vec3::new(1.0 - v - w, v, w)
+}
+
+/// Pairs of overlapping boxes, sorted along the x axis
+pub fn sweep_and_prune(bounds: &[Aabb]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..bounds.len()).collect();
+    order.sort_by(|&a, &b| bounds[a].min.x.total_cmp(&bounds[b].min.x));
+
+    let mut active: Vec<usize> = Vec::new();
+    let mut pairs = Vec::new();
+    for i in order {
+        active.retain(|&j| bounds[j].max.x >= bounds[i].min.x);
+        for &j in &active {
+            if bounds[i].overlaps(&bounds[j]) {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+        active.push(i);
+    }
+    pairs
+}
+
+/// Slots are reused after a removal, the generation tells a stale id from the collider now in its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColliderId {
+    index: usize,
+    generation: u32,
+}
+
+impl ColliderId {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionPair {
+    pub a: ColliderId,
+    pub b: ColliderId,
+    /// Contact seen from `a`
+    pub contact: Contact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Enter { trigger: ColliderId, other: ColliderId },
+    Exit { trigger: ColliderId, other: ColliderId },
+}
+
+struct Body {
+    collider: Collider,
+    transform: Mat4,
+    trigger: bool,
+}
+
+#[derive(Default)]
+struct Slot {
+    generation: u32,
+    body: Option<Body>,
+}
+
+impl Slot {
+    fn get(&self, generation: u32) -> Option<&Body> {
+        self.body.as_ref().filter(|_| self.generation == generation)
+    }
+}
+
+/// Set of colliders checked against each other on `update`
+pub struct CollisionWorld {
+    bodies: Vec<Slot>,
+    contacts: Vec<CollisionPair>,
+    overlaps: HashSet<(ColliderId, ColliderId)>,
+    events: Vec<TriggerEvent>,
+}
+
+impl CollisionWorld {
+    pub fn new() -> Self {
+        Self {
+            bodies: Vec::new(),
+            contacts: Vec::new(),
+            overlaps: HashSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, collider: Collider, transform: &Mat4) -> ColliderId {
+        self.insert(Body {
+            collider,
+            transform: *transform,
+            trigger: false,
+        })
+    }
+
+    /// Trigger volumes don't produce contacts, only enter and exit events
+    pub fn add_trigger(&mut self, collider: Collider, transform: &Mat4) -> ColliderId {
+        self.insert(Body {
+            collider,
+            transform: *transform,
+            trigger: true,
+        })
+    }
+
+    fn insert(&mut self, body: Body) -> ColliderId {
+        let index = match self.bodies.iter().position(|slot| slot.body.is_none()) {
+            Some(index) => index,
+            None => {
+                self.bodies.push(Slot::default());
+                self.bodies.len() - 1
+            }
+        };
+        let slot = &mut self.bodies[index];
+        slot.body = Some(body);
+        ColliderId { index, generation: slot.generation }
+    }
+
+    /// Exit events for the triggers still overlapping it are sent on the next update
+    pub fn remove(&mut self, id: ColliderId) {
+        if let Some(slot) = self.bodies.get_mut(id.index).filter(|slot| slot.get(id.generation).is_some()) {
+            slot.body = None;
+            slot.generation += 1;
+        }
+    }
+
+    pub fn contains(&self, id: ColliderId) -> bool {
+        self.body(id).is_some()
+    }
+
+    fn body(&self, id: ColliderId) -> Option<&Body> {
+        self.bodies.get(id.index).and_then(|slot| slot.get(id.generation))
+    }
+
+    fn body_mut(&mut self, id: ColliderId) -> Option<&mut Body> {
+        self.bodies
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.body.as_mut())
+    }
+
+    fn id(&self, index: usize) -> ColliderId {
+        ColliderId { index, generation: self.bodies[index].generation }
+    }
+
+    pub fn collider(&self, id: ColliderId) -> Option<&Collider> {
+        self.body(id).map(|body| &body.collider)
+    }
+
+    pub fn transform(&self, id: ColliderId) -> Option<&Mat4> {
+        self.body(id).map(|body| &body.transform)
+    }
+
+    pub fn is_trigger(&self, id: ColliderId) -> bool {
+        self.body(id).is_some_and(|body| body.trigger)
+    }
+
+    /// Moves a collider, typically to follow `Object::transform`
+    pub fn set_transform(&mut self, id: ColliderId, transform: &Mat4) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.transform = *transform;
+        }
+        self
+    }
+
+    pub fn set_collider(&mut self, id: ColliderId, collider: Collider) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.collider = collider;
+        }
+        self
+    }
+
+    /// Finds the contacts and trigger events for the current transforms
+    pub fn update(&mut self) {
+        let ids: Vec<ColliderId> = (0..self.bodies.len())
+            .filter(|&i| self.bodies[i].body.is_some())
+            .map(|i| self.id(i))
+            .collect();
+        let bodies: Vec<&Body> = ids.iter().filter_map(|id| self.body(*id)).collect();
+        let bounds: Vec<Aabb> = bodies.iter().map(|body| body.collider.bounds(&body.transform)).collect();
+
+        let mut contacts = Vec::new();
+        let mut overlaps = HashSet::new();
+        for (i, j) in sweep_and_prune(&bounds) {
+            let (a, b) = (bodies[i], bodies[j]);
+            if a.trigger && b.trigger {
+                continue;
+            }
+            let Some(contact) = collide(&a.collider, &a.transform, &b.collider, &b.transform) else {
+                continue;
+            };
+
+            if a.trigger {
+                overlaps.insert((ids[i], ids[j]));
+            } else if b.trigger {
+                overlaps.insert((ids[j], ids[i]));
+            } else {
+                contacts.push(CollisionPair {
+                    a: ids[i],
+                    b: ids[j],
+                    contact,
+                });
+            }
+        }
+
+        self.events.clear();
+        let mut exits: Vec<_> = self.overlaps.difference(&overlaps).copied().collect();
+        let mut enters: Vec<_> = overlaps.difference(&self.overlaps).copied().collect();
+        exits.sort();
+        enters.sort();
+        self.events.extend(exits.into_iter().map(|(trigger, other)| TriggerEvent::Exit { trigger, other }));
+        self.events.extend(enters.into_iter().map(|(trigger, other)| TriggerEvent::Enter { trigger, other }));
+
+        self.contacts = contacts;
+        self.overlaps = overlaps;
+    }
+
+    pub fn contacts(&self) -> &[CollisionPair] {
+        &self.contacts
+    }
+
+    /// Events produced by the last update
+    pub fn trigger_events(&self) -> &[TriggerEvent] {
+        &self.events
+    }
+
+    pub fn is_inside(&self, trigger: ColliderId, other: ColliderId) -> bool {
+        self.overlaps.contains(&(trigger, other))
+    }
+
+    /// Contacts of a collider that is not part of the world against the solid colliders,
+    /// each contact is seen from the queried collider
+    pub fn overlaps(&self, collider: &Collider, transform: &Mat4) -> Vec<(ColliderId, Contact)> {
+        let bounds = collider.bounds(transform);
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| {
+                let body = slot.body.as_ref().filter(|body| !body.trigger)?;
+                if !bounds.overlaps(&body.collider.bounds(&body.transform)) {
+                    return None;
+                }
+                collide(collider, transform, &body.collider, &body.transform).map(|contact| (self.id(i), contact))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Quat};
+
+    use super::*;
+
+    fn at(position: Vec3) -> Mat4 {
+        Mat4::from_translation(position)
+    }
+
+    #[test]
+    fn analytic_contacts() {
+        let sphere = Collider::sphere(1.0);
+        let contact = collide(&sphere, &at(Vec3::ZERO), &sphere, &at(vec3(1.5, 0.0, 0.0))).unwrap();
+        assert!(contact.normal.abs_diff_eq(Vec3::X, 1.0e-5));
+        assert!((contact.depth - 0.5).abs() < 1.0e-5);
+        assert!(contact.point.abs_diff_eq(vec3(0.75, 0.0, 0.0), 1.0e-5));
+
+        let aabb = Collider::aabb(Vec3::splat(0.5));
+        let contact = collide(&aabb, &at(Vec3::ZERO), &aabb, &at(vec3(0.2, 0.9, 0.0))).unwrap();
+        assert_eq!(contact.normal, Vec3::Y);
+        assert!((contact.depth - 0.1).abs() < 1.0e-5);
+
+        let capsule = Collider::capsule(0.5, 1.0);
+        let contact = collide(&capsule, &at(Vec3::ZERO), &sphere, &at(vec3(0.0, -2.0, 0.0))).unwrap();
+        assert!(contact.normal.abs_diff_eq(-Vec3::Y, 1.0e-5));
+        assert!((contact.depth - 0.5).abs() < 1.0e-5);
+
+        assert!(!intersects(&sphere, &at(Vec3::ZERO), &aabb, &at(vec3(2.0, 0.0, 0.0))));
+    }
+
+    #[test]
+    fn convex_contacts_match_analytic_ones() {
+        let cube = Collider::convex_hull(
+            (0..8).map(|i| vec3((i & 1) as f32 - 0.5, ((i >> 1) & 1) as f32 - 0.5, ((i >> 2) & 1) as f32 - 0.5)).collect(),
+        );
+        let contact = collide(&cube, &at(Vec3::ZERO), &cube, &at(vec3(0.1, 0.0, 0.8))).unwrap();
+        assert!(contact.normal.abs_diff_eq(Vec3::Z, 1.0e-3));
+        assert!((contact.depth - 0.2).abs() < 1.0e-3);
+
+        let rotated = Mat4::from_rotation_translation(Quat::from_rotation_y(0.3), vec3(3.0, 0.0, 0.0));
+        assert!(collide(&cube, &at(Vec3::ZERO), &cube, &rotated).is_none());
+
+        let sphere = Collider::sphere(0.5);
+        let contact = collide(&cube, &at(Vec3::ZERO), &sphere, &at(vec3(0.0, 0.8, 0.0))).unwrap();
+        assert!(contact.normal.abs_diff_eq(Vec3::Y, 1.0e-2));
+        assert!((contact.depth - 0.2).abs() < 1.0e-2);
+    }
+
+    #[test]
+    fn world_contacts_and_trigger_events() {
+        let mut world = CollisionWorld::new();
+        let ground = world.add(Collider::aabb(vec3(10.0, 0.5, 10.0)), &at(vec3(0.0, -0.5, 0.0)));
+        let ball = world.add(Collider::sphere(0.5), &at(vec3(0.0, 0.4, 0.0)));
+        let zone = world.add_trigger(Collider::aabb(Vec3::ONE), &at(vec3(5.0, 1.0, 0.0)));
+
+        world.update();
+        assert_eq!(world.contacts().len(), 1);
+        assert_eq!((world.contacts()[0].a, world.contacts()[0].b), (ground, ball));
+        assert!(world.trigger_events().is_empty());
+
+        world.set_transform(ball, &at(vec3(5.0, 1.0, 0.0)));
+        world.update();
+        assert!(world.contacts().is_empty());
+        assert_eq!(world.trigger_events(), &[TriggerEvent::Enter { trigger: zone, other: ball }]);
+
+        world.update();
+        assert!(world.trigger_events().is_empty());
+
+        // the slot of the ball is reused before the update
+        world.remove(ball);
+        let crate_box = world.add(Collider::aabb(Vec3::splat(0.5)), &at(vec3(5.0, 1.0, 0.0)));
+        assert_eq!(crate_box.index(), ball.index());
+        assert!(!world.contains(ball));
+        world.set_transform(ball, &at(Vec3::ZERO));
+        assert_eq!(world.transform(crate_box), Some(&at(vec3(5.0, 1.0, 0.0))));
+
+        world.update();
+        assert_eq!(world.trigger_events(), &[
+            TriggerEvent::Exit { trigger: zone, other: ball },
+            TriggerEvent::Enter { trigger: zone, other: crate_box },
+        ]);
+    }
+
+    #[test]
+    fn sweep_and_prune_finds_overlapping_pairs() {
+        let bounds: Vec<Aabb> = (0..100).map(|i| Aabb::from_center(vec3(i as f32 * 0.75, 0.0, 0.0), Vec3::splat(0.5))).collect();
+        let pairs = sweep_and_prune(&bounds);
+        assert_eq!(pairs.len(), 99);
+        assert!(pairs.iter().all(|(a, b)| b - a == 1));
+    }
+}
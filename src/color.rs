@@ -1,6 +1,8 @@
 use std::ops::{Add, Mul, Sub, AddAssign, SubAssign, MulAssign, Div, DivAssign};
 
-#[derive(Debug, Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -44,6 +46,12 @@ impl Color {
     }
 }
 
+impl From<[f32; 4]> for Color {
+    fn from(value: [f32; 4]) -> Self {
+        Color::new(value[0], value[1], value[2], value[3])
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Color::black()
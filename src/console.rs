@@ -1,7 +1,9 @@
+use std::{path::PathBuf, thread::JoinHandle};
+
 use glam::uvec2;
 use miniquad::EventHandler;
 
-use crate::{game::Game, graphics::Graphics, gui::Gui, inputs::Inputs, renderer::{Renderer, RendererData}};
+use crate::{capture::{capture_path, CaptureError, GifRecorder}, game::Game, graphics::Graphics, gui::Gui, inputs::Inputs, renderer::{Renderer, RendererData}, resolution::Resolution};
 
 pub trait System {
     fn init(&mut self);
@@ -15,32 +17,70 @@ pub trait System {
     fn key_up(&mut self, keycode: miniquad::KeyCode, keymods: miniquad::KeyMods) {}
 }
 
+/// Cycles through the render modes
+const RENDER_MODE_KEY: miniquad::KeyCode = miniquad::KeyCode::F3;
+/// Saves the image as a PNG in the working directory
+const SCREENSHOT_KEY: miniquad::KeyCode = miniquad::KeyCode::F12;
+/// Saves the last recorded seconds as a GIF in the working directory, see `Config::gif_duration`
+const GIF_KEY: miniquad::KeyCode = miniquad::KeyCode::F11;
+
+/// Settings of the cartridge, applied when the console boots
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Can be changed at runtime with `Graphics::set_resolution`
+    pub resolution: Resolution,
+    /// Integer upscale of the screenshots saved with `SCREENSHOT_KEY`
+    pub screenshot_scale: u32,
+    /// Seconds saved with `GIF_KEY`, nothing is recorded when `None`.
+    /// Recording reads the image back from the GPU and keeps its frames in memory.
+    pub gif_duration: Option<f32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution::default(),
+            screenshot_scale: 1,
+            gif_duration: None,
+        }
+    }
+}
+
 // The Polytron console
 pub struct Console {
+    config: Config,
     data: RendererData,
     renderer: Renderer,
     game: Game,
     game_init: bool,
     gui: Gui,
     inputs: Inputs,
+    /// GIFs being encoded in the background, their failures are stored with the capture errors
+    gif_saves: Vec<(PathBuf, JoinHandle<Result<(), CaptureError>>)>,
 }
 
 impl Console {
-    pub fn boot() {
+    pub fn boot(config: Config) {
         let conf = miniquad::conf::Conf {
             fullscreen: true,
             ..Default::default()
         };
 
         miniquad::start(conf, move || {
+            let mut data = RendererData::new();
+            data.recorder = config.gif_duration.map(GifRecorder::new);
+            Graphics { data: &mut data }.set_resolution(config.resolution);
+            let renderer = Renderer::new(config.resolution.size);
             Box::new(
                 Self {
-                    data: RendererData::new(),
-                    renderer: Renderer::new(),
+                    config,
+                    data,
+                    renderer,
                     game: Game::new(),
                     game_init: false,
                     gui: Gui {},
                     inputs: Inputs::new(),
+                    gif_saves: Vec::new(),
                 }
             )
         });
@@ -50,28 +90,45 @@ impl Console {
 impl EventHandler for Console {
     fn update(&mut self) {
         if !self.game_init {
+            let (width, height) = miniquad::window::screen_size();
+            self.inputs.resize_event(width, height);
             self.game.init();
             self.game_init = true;
         }
 
         self.game.update(&self.inputs);
         self.inputs.reset();
+
+        for (path, save) in std::mem::take(&mut self.gif_saves) {
+            if !save.is_finished() {
+                self.gif_saves.push((path, save));
+            } else {
+                match save.join() {
+                    Ok(Ok(())) => (),
+                    Ok(Err(err)) => self.data.capture_errors.push((path, err)),
+                    Err(_) => self.data.capture_errors.push((path, CaptureError::Panicked)),
+                }
+            }
+        }
     }
 
     fn draw(&mut self) {
         self.data.begin_frame();
 
-        self.game.draw(
-            &mut Graphics {
-                data: &mut self.data
-            }
-        );
+        let mut graphics = Graphics {
+            data: &mut self.data
+        };
+        self.game.draw(&mut graphics);
+        graphics.flush_transparent();
+        graphics.flush_debug_lines();
+        graphics.flush_canvas();
         self.renderer.draw(&mut self.data);
         //self.renderer.draw_ui(&mut self.gui);
         self.renderer.commit_frame();
     }
 
     fn resize_event(&mut self, _width: f32, _height: f32) {
+        self.inputs.resize_event(_width, _height);
         self.renderer.set_screen_resolution(uvec2(_width as u32, _height as u32));
     }
 
@@ -84,6 +141,7 @@ impl EventHandler for Console {
     }
 
     fn mouse_wheel_event(&mut self, dx: f32, dy: f32) {
+        self.inputs.mouse_wheel_event(dx, dy);
         self.game.mouse_wheel(dx, dy);
         self.renderer
         .egui_mq_mut()
@@ -91,6 +149,7 @@ impl EventHandler for Console {
     }
 
     fn mouse_button_down_event(&mut self, mb: miniquad::MouseButton, x: f32, y: f32) {
+        self.inputs.mouse_button_down_event(mb);
         self.game.mouse_button_down(mb, x, y);
         self.renderer
         .egui_mq_mut()
@@ -98,6 +157,7 @@ impl EventHandler for Console {
     }
 
     fn mouse_button_up_event(&mut self, mb: miniquad::MouseButton, x: f32, y: f32) {
+        self.inputs.mouse_button_up_event(mb);
         self.game.mouse_button_up(mb, x, y);
         self.renderer
         .egui_mq_mut()
@@ -110,9 +170,21 @@ impl EventHandler for Console {
         .char_event(character);
     }
 
-    fn key_down_event(&mut self, keycode: miniquad::KeyCode, keymods: miniquad::KeyMods, _repeat: bool) {
+    fn key_down_event(&mut self, keycode: miniquad::KeyCode, keymods: miniquad::KeyMods, repeat: bool) {
         self.inputs.key_down_event(keycode);
-        self.game.key_down(keycode, keymods, _repeat);
+        if keycode == RENDER_MODE_KEY && !repeat {
+            self.data.render_mode = self.data.render_mode.next();
+        }
+        if keycode == SCREENSHOT_KEY && !repeat {
+            Graphics { data: &mut self.data }.save_screenshot(capture_path("png"), self.config.screenshot_scale);
+        }
+        if keycode == GIF_KEY && !repeat {
+            let path = capture_path("gif");
+            if let Some(save) = (Graphics { data: &mut self.data }).save_gif(&path) {
+                self.gif_saves.push((path, save));
+            }
+        }
+        self.game.key_down(keycode, keymods, repeat);
         self.renderer
         .egui_mq_mut()
         .key_down_event(keycode, keymods);
@@ -0,0 +1,174 @@
+use glam::{vec3, Mat4, Vec3, Vec4Swizzles};
+
+use crate::{collision::Aabb, color::Color, graphics::{Camera, Graphics, Vertex}, material::Material, renderer::Primitive};
+
+/// Segments of each circle drawn by `Graphics::draw_sphere`
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// Line drawn by every 3D camera until it expires, see `Graphics::debug_line_for`
+pub struct TimedLine {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub color: Color,
+    /// Time from `miniquad::date::now` after which the line is removed
+    pub until: f64,
+}
+
+/// Immediate-mode debug drawing, the lines of a camera are batched into a single draw call
+/// when the camera changes or the frame ends.
+/// Everything is skipped in release builds, without `debug_assertions`.
+impl Graphics<'_> {
+    pub fn debug_line(&mut self, start: Vec3, end: Vec3, color: Color) -> &mut Self {
+        if cfg!(debug_assertions) {
+            let color = color.as_array();
+            for position in [start, end] {
+                self.data.debug_lines.push(Vertex {
+                    position: position.to_array(),
+                    color,
+                    normal: [0.0, 0.0, 0.0],
+                    uv: [0.0, 0.0],
+                });
+            }
+        }
+        self
+    }
+
+    /// Keeps a line on screen for `duration` seconds
+    pub fn debug_line_for(&mut self, start: Vec3, end: Vec3, color: Color, duration: f32) -> &mut Self {
+        if cfg!(debug_assertions) {
+            self.data.timed_lines.push(TimedLine {
+                start,
+                end,
+                color,
+                until: miniquad::date::now() + duration as f64,
+            });
+        }
+        self
+    }
+
+    /// Square grid in the XZ plane, `cells` wide along both axes
+    pub fn draw_grid(&mut self, center: Vec3, cells: u32, spacing: f32, color: Color) -> &mut Self {
+        let half_size = cells as f32 * spacing * 0.5;
+        for i in 0..=cells {
+            let offset = i as f32 * spacing - half_size;
+            self.debug_line(center + vec3(offset, 0.0, -half_size), center + vec3(offset, 0.0, half_size), color);
+            self.debug_line(center + vec3(-half_size, 0.0, offset), center + vec3(half_size, 0.0, offset), color);
+        }
+        self
+    }
+
+    /// X, Y and Z axes of the local space of `transform`, in red, green and blue
+    pub fn draw_axes(&mut self, transform: &Mat4, length: f32) -> &mut Self {
+        let origin = transform.w_axis.xyz();
+        self.debug_line(origin, transform.transform_point3(Vec3::X * length), Color::red())
+            .debug_line(origin, transform.transform_point3(Vec3::Y * length), Color::green())
+            .debug_line(origin, transform.transform_point3(Vec3::Z * length), Color::blue())
+    }
+
+    pub fn draw_aabb(&mut self, aabb: &Aabb, color: Color) -> &mut Self {
+        let corners = box_corners(|corner| aabb.min + (aabb.max - aabb.min) * corner);
+        self.draw_box(corners, color)
+    }
+
+    /// Three circles around the X, Y and Z axes
+    pub fn draw_sphere(&mut self, center: Vec3, radius: f32, color: Color) -> &mut Self {
+        self.debug_circle(center, Vec3::X * radius, Vec3::Y * radius, color)
+            .debug_circle(center, Vec3::Y * radius, Vec3::Z * radius, color)
+            .debug_circle(center, Vec3::Z * radius, Vec3::X * radius, color)
+    }
+
+    /// Volume seen by a camera, between its clip planes
+    pub fn draw_frustum(&mut self, camera: &dyn Camera, color: Color) -> &mut Self {
+        let inverse_view_proj = camera.view_proj(&self.data.resolution).inverse();
+        let corners = box_corners(|corner| inverse_view_proj.project_point3(corner * 2.0 - 1.0));
+        self.draw_box(corners, color)
+    }
+
+    pub fn draw_arrow(&mut self, start: Vec3, end: Vec3, color: Color) -> &mut Self {
+        self.debug_line(start, end, color);
+        let Some(direction) = (end - start).try_normalize() else {
+            return self;
+        };
+        let head_length = start.distance(end) * 0.2;
+        let (side, up) = direction.any_orthonormal_pair();
+        let base = end - direction * head_length;
+        for offset in [side, -side, up, -up] {
+            self.debug_line(end, base + offset * head_length * 0.5, color);
+        }
+        self
+    }
+
+    /// Draws the debug lines of the current camera, done when the camera changes
+    pub fn flush_debug_lines(&mut self) -> &mut Self {
+        if self.data.debug_lines.is_empty() {
+            return self;
+        }
+        let mut vertices = std::mem::take(&mut self.data.debug_lines);
+        let indices = (0..vertices.len() as i32).collect();
+        self.new_draw_call(&vertices, &indices, &Mat4::IDENTITY, Primitive::Lines, &Material::default());
+        vertices.clear();
+        self.data.debug_lines = vertices;
+        self
+    }
+
+    /// Adds the timed lines to the lines of a new 3D camera
+    pub(crate) fn queue_timed_lines(&mut self) {
+        let timed_lines = std::mem::take(&mut self.data.timed_lines);
+        for line in &timed_lines {
+            self.debug_line(line.start, line.end, line.color);
+        }
+        self.data.timed_lines = timed_lines;
+    }
+
+    fn debug_circle(&mut self, center: Vec3, u: Vec3, v: Vec3, color: Color) -> &mut Self {
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + u * angle.cos() + v * angle.sin()
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.debug_line(point(i), point(i + 1), color);
+        }
+        self
+    }
+
+    fn draw_box(&mut self, corners: [Vec3; 8], color: Color) -> &mut Self {
+        // corners differing by one bit of their index share an edge
+        for (i, corner) in corners.iter().enumerate() {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.debug_line(*corner, corners[i | bit], color);
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Corners of a box from their position in the unit cube, the bits of the index select the max side of x, y and z
+fn box_corners(corner: impl Fn(Vec3) -> Vec3) -> [Vec3; 8] {
+    std::array::from_fn(|i| corner(vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::RendererData;
+
+    use super::*;
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn debug_lines_are_batched() {
+        let mut data = RendererData::new();
+        let mut graphics = Graphics { data: &mut data };
+        graphics
+            .draw_grid(Vec3::ZERO, 10, 1.0, Color::white())
+            .draw_aabb(&Aabb::new(-Vec3::ONE, Vec3::ONE), Color::green())
+            .draw_arrow(Vec3::ZERO, Vec3::Y, Color::red())
+            .flush_debug_lines();
+
+        assert_eq!(data.draw_calls_count, 1);
+        // 22 grid lines, 12 box edges and 5 arrow lines
+        assert_eq!(data.draw_calls[0].vertices.len(), (22 + 12 + 5) * 2);
+        assert!(data.debug_lines.is_empty());
+    }
+}
@@ -1,37 +1,95 @@
 use std::f32::consts::PI;
 
-use glam::{vec2, vec3, vec4, Mat4, Vec3, Vec4};
+use glam::{vec2, vec3, Mat4, Quat, Vec3};
+use miniquad::KeyCode;
 
-use crate::{color::Color, console::System, graphics::{Camera2d, Camera3d, Graphics, Rect2d}, inputs::Inputs, object::Object, time::TimeStep};
-
-const LOOK_SPEED: f32 = 0.1;
-const MOVE_SPEED: f32 = 5.0;
+use crate::{billboard::Billboard, camera_controller::FirstPersonController, character::CharacterController, collision::{Collider, ColliderId, CollisionWorld}, color::Color, console::System, graphics::{Camera2d, Camera3d, Fog, Graphics, Rect2d}, inputs::Inputs, light::{Light, Shadow}, material::Material, object::Object, particles::ParticleEmitter, resolution::Resolution, sky::Sky, texture::Texture, time::TimeStep};
 
 pub struct Game {
     time_step: TimeStep,
     camera_3d: Camera3d,
     camera_2d: Camera2d,
+    monitor_camera: Camera3d,
+    camera_controller: FirstPersonController,
+    character: CharacterController,
+    level: CollisionWorld,
+    cube_collider: ColliderId,
     cube: Object,
     plane: Object,
-    pitch: f32,
-    yaw: f32,
+    monitor: Object,
+    sparks: ParticleEmitter,
+    tree: Billboard,
+    sun: Light,
+    /// Picked with F4, the resolution of the console configuration is kept until then
+    resolution: Option<Resolution>,
 }
 
 impl Default for Game {
     fn default() -> Self {
-        let camera_3d = Camera3d::new();
+        let camera_3d = Camera3d::new()
+            .with_background(Color::new(0.1, 0.1, 0.15, 1.0))
+            .with_fog(Fog::linear(4.0, 12.0).with_depth_cue(0.3))
+            .with_sky(
+                Sky::gradient(Color::new(0.05, 0.05, 0.3, 1.0), Color::new(0.1, 0.1, 0.15, 1.0), Color::black())
+                    .with_sun(vec3(1.0, 2.0, 1.0), Color::new(1.0, 0.9, 0.6, 1.0), 0.05)
+            );
         //.with_viewport(&Rect2d {position: vec2(0.0, 0.0), size: vec2(0.5, 1.0)});
         let camera_2d = Camera2d::new();
         //.with_viewport(&Rect2d {position: vec2(0.5, 0.0), size: vec2(0.5, 1.0)})
         //.with_background(Color::new(0.1, 0.1, 0.1, 1.0));
+        // a screen in the level showing the cube from above
+        let monitor_target = Texture::render_target(64, 64);
+        let monitor_camera = Camera3d::new()
+            .with_translation(vec3(0.0, 3.0, 3.0))
+            .with_look_at(Vec3::ZERO, Vec3::Y)
+            .with_background(Color::new(0.0, 0.1, 0.0, 1.0))
+            .with_target(&monitor_target);
+        let monitor = Object::new_plane(Color::white())
+            .with_transform(&Mat4::from_scale_rotation_translation(
+                Vec3::splat(2.0),
+                Quat::from_rotation_x(PI / 2.0),
+                vec3(-3.0, 0.5, -3.0),
+            ))
+            .with_material(Material::unlit().with_texture(monitor_target));
+        let cube = Object::new_cube(Color::red())
+            .with_material(
+                Material::new()
+                    .with_color(Color::red())
+                    .with_specular(0.5, 16.0)
+                    .with_emissive(Color::new(0.1, 0.0, 0.0, 0.0))
+            );
+        let mut level = CollisionWorld::new();
+        let cube_collider = level.add(Collider::from_object(&cube), cube.transform());
+        level.add(Collider::aabb(vec3(5.0, 0.5, 5.0)), &Mat4::from_translation(vec3(0.0, -1.5, 0.0)));
         Self { 
             time_step: Default::default(),
+            camera_controller: FirstPersonController::from_camera(&camera_3d),
+            character: CharacterController::new(vec3(0.0, -1.0, 5.0)),
+            level,
+            cube_collider,
             camera_3d,
             camera_2d,
-            cube: Object::new_cube(Color::red()),
+            monitor_camera,
+            cube,
             plane: Object::new_plane(Color::white()),
-            yaw: PI / 2.0,
-            pitch: 0.0,
+            monitor,
+            sparks: ParticleEmitter::sparks(vec3(0.0, 1.0, 0.0), Color::new(1.0, 0.6, 0.1, 1.0)),
+            tree: Billboard::new(vec3(3.0, 0.0, -3.0), vec2(1.0, 2.0))
+                .with_texture(Texture::from_fn(8, 16, |x, y| {
+                    let half_width = if y < 12 { y as i32 / 3 + 1 } else { 1 };
+                    if (x as i32 * 2 - 7).abs() > half_width * 2 {
+                        Color::new(0.0, 0.0, 0.0, 0.0)
+                    } else if y < 12 {
+                        Color::green()
+                    } else {
+                        Color::new(0.5, 0.3, 0.1, 1.0)
+                    }
+                }))
+                .with_axis(Vec3::Y),
+            sun: Light::new()
+                .with_direction(vec3(-1.0, -2.0, -1.0))
+                .with_shadow(Shadow::new().with_resolution(128).with_area(6.0, 20.0)),
+            resolution: None,
         }
     }
 }
@@ -58,85 +116,55 @@ impl System for Game {
         .rotate_z((PI / 4.0) * dt)
         .rotate_y((PI / 4.0) * dt);
 
-        let dt = self.time_step.delta_time();
-        self.camera_3d.translate( 
-            if inputs.key(miniquad::KeyCode::W) {
-                vec3(0.0, 0.0, -1.0)
-            } else if inputs.key(miniquad::KeyCode::S) {
-                vec3(0.0, 0.0, 1.0)
-            } else if inputs.key(miniquad::KeyCode::D) {
-                vec3(1.0, 0.0, 0.0)
-            } else if inputs.key(miniquad::KeyCode::A) {
-                vec3(-1.0, 0.0, 0.0)
-            }else {
-                vec3(0.0, 0.0, 0.0)
-            }
-            * dt
-            * MOVE_SPEED
-        );
-
-        if inputs.key(miniquad::KeyCode::Q) {
-            self.camera_3d.rotate_y((PI / 2.0) * dt);
-        }
+        self.level.set_transform(self.cube_collider, self.cube.transform());
+        self.sparks.advance(dt);
 
-        if inputs.key(miniquad::KeyCode::E) {
-            self.camera_3d.rotate_y((-PI / 2.0) * dt);
+        self.camera_controller.look(inputs);
+        if inputs.key(KeyCode::Space) {
+            self.character.jump();
         }
-
-        self.yaw -= inputs.mouse_delta().x * LOOK_SPEED * dt;
-        self.pitch += inputs.mouse_delta().y * LOOK_SPEED * dt;
-
-        self.pitch = if self.pitch > 1.5 { 1.5 } else { self.pitch };
-        self.pitch = if self.pitch < -1.5 { -1.5 } else { self.pitch };
-
-        let front = vec3(
-            self.yaw.cos() * self.pitch.cos(),
-            self.pitch.sin(),
-            self.yaw.sin() * self.pitch.cos(),
-        )
-        .normalize();
-
-        let right = front.cross(vec3(0.0, 1.0, 0.0)).normalize();
-        let up = right.cross(front).normalize();
-
-        let new_mat = Mat4::from_cols(
-            vec4(right.x, right.y, right.z, 0.0),
-            vec4(up.x, up.y, up.z, 0.0),
-            vec4(front.x, front.y, front.z, 0.0),
-              vec4(self.camera_3d.position().x, self.camera_3d.position().y, self.camera_3d.position().z, 1.0)
-        );
-
-        self.camera_3d.set_transform(&new_mat);
+        self.character.update(self.camera_controller.movement(inputs, dt), &self.level, dt);
+        self.camera_3d.set_transform(&Mat4::from_rotation_translation(
+            self.camera_controller.rotation(),
+            self.character.eye_position(),
+        ));
     }
 
     fn draw(&self, g: &mut Graphics) {
-        g
-        .set_camera(&self.camera_3d);
-
-        for x in -5..6 {
-            g.draw_line(
-                vec3(x as f32, -1.0, -5.0), 
-                vec3(x as f32, -1.0, 5.0), 
-                Color::white()
-            );
+        if let Some(resolution) = self.resolution {
+            g.set_resolution(resolution);
         }
-        for z in -5..6 {
-            g.draw_line(
-                vec3(-5.0, -1.0, z as f32), 
-                vec3(5.0, -1.0, z as f32), 
-                Color::white()
-            );
-        }
-
-        g.draw_object(&self.cube)
+        g
+        .set_light(&self.sun)
+        .set_camera(&self.monitor_camera)
+        .draw_object(&self.plane)
+        .draw_object(&self.cube)
+        .set_camera(&self.camera_3d)
+        .draw_object(&self.plane)
+        .draw_object(&self.monitor)
+        .draw_grid(vec3(0.0, -1.0, 0.0), 10, 1.0, Color::white())
+        .draw_axes(self.cube.transform(), 1.5)
+        .draw_object(&self.cube)
+        .draw_particles(&self.sparks)
+        .draw_blob_shadow(vec3(3.0, -1.0, -3.0), 0.4, 0.6)
+        .draw_billboard(&self.tree)
         .draw_rectangle(vec2(-1.0, -1.0), vec2(2.0, 2.0), Color::blue())
         .draw_line(vec3(-1.0, -1.0, 0.0), vec3(1.0, 1.0, 0.0), Color::green())
         .set_camera(&self.camera_2d)
         .draw_line(vec3(0.0, 0.0, 0.0), vec3(320.0, 200.0, 0.0), Color::green())
-        .draw_rectangle(vec2(10.0, 10.0), vec2(100.0, 50.0), Color::gray());
+        .draw_rectangle(vec2(10.0, 10.0), vec2(100.0, 50.0), Color::gray())
+        .draw_rounded_rectangle_outline(vec2(10.0, 70.0), vec2(60.0, 30.0), 6.0, 2.0, Color::white())
+        .draw_circle(vec2(280.0, 30.0), 12.0, Color::new(1.0, 0.9, 0.2, 1.0))
+        .draw_arc(vec2(280.0, 30.0), 16.0, 0.0, PI, 1.0, Color::white())
+        .draw_polygon(&[vec2(20.0, 180.0), vec2(60.0, 150.0), vec2(40.0, 170.0), vec2(80.0, 190.0)], Color::green())
+        .pset(160, 100, Color::red());
     }
 
     fn key_down(&mut self, keycode: miniquad::KeyCode, keymods: miniquad::KeyMods, _repeat: bool) {
-        
+        if keycode == KeyCode::F4 {
+            let resolutions = [Resolution::DEFAULT, Resolution::HANDHELD, Resolution::HOME_CONSOLE, Resolution::VGA, Resolution::WIDESCREEN];
+            let current = resolutions.iter().position(|resolution| Some(*resolution) == self.resolution).unwrap_or(0);
+            self.resolution = Some(resolutions[(current + 1) % resolutions.len()]);
+        }
     }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 use std::f32::consts::PI;
 
-use glam::{vec2, vec3, Mat4, Vec2, Vec3, Vec4Swizzles};
+use glam::{vec2, vec3, EulerRot, Mat4, Quat, Vec2, Vec3, Vec4Swizzles};
+use serde::{Deserialize, Serialize};
 
-use crate::{color::Color, object::Object, renderer::{DrawCall, Mode, Primitive, RendererData, IMAGE_RATIO_XY, IMAGE_RES}};
+use crate::{billboard::{camera_basis, Billboard}, color::Color, light::Light, material::{BlendMode, Material}, object::Object, particles::{Particle, ParticleEmitter}, renderer::{image_to_screen, screen_to_image, DrawCall, Mode, Primitive, RenderMode, RendererData, SkyDraw, TransparentDraw}, resolution::Resolution, scene::{NodeContent, Scene}, sky::Sky, texture::Texture};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Rect2d {
     pub position: Vec2,
     pub size: Vec2,
@@ -16,25 +17,129 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 4],
     pub normal: [f32; 3],
+    /// Texture coordinates, with a top left origin
+    pub uv: [f32; 2],
 }
 
 pub trait Camera {
-    fn view_proj(&self) -> Mat4;
+    /// `resolution` is the one of the image, used by the cameras without a target
+    fn view_proj(&self, resolution: &Resolution) -> Mat4;
+    /// Placement of the camera in the world, its axes are the right, up and backward directions
+    fn world_transform(&self, resolution: &Resolution) -> Mat4;
     fn mode(&self) -> Mode;
     fn viewport(&self) -> &Rect2d;
     fn background(&self) -> Color;
+    fn fog(&self) -> Option<Fog>;
+    fn sky(&self) -> Option<&Sky>;
+    /// Render target drawn instead of the image
+    fn target(&self) -> Option<&Texture>;
+}
+
+/// Size in pixels of the image or of the render target a camera draws into
+fn surface_size(target: Option<&Texture>, resolution: &Resolution) -> Vec2 {
+    target.map_or(resolution.as_vec2(), |target| target.size().as_vec2())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FogFalloff {
+    /// Clear before `start`, opaque after `end`
+    Linear { start: f32, end: f32 },
+    /// `1 - exp(-density * distance)`
+    Exponential { density: f32 },
+    /// `1 - exp(-(density * distance)²)`, clearer near the camera
+    ExponentialSquared { density: f32 },
+}
+
+/// Fades the 3D geometry with its distance to the camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fog {
+    pub falloff: FogFalloff,
+    /// Blends to the camera background when `None`
+    pub color: Option<Color>,
+    /// Darkening of far vertices, from 0 (none) to 1 (black where the fog is opaque)
+    pub depth_cue: f32,
+}
+
+impl Fog {
+    pub fn linear(start: f32, end: f32) -> Self {
+        Self {
+            falloff: FogFalloff::Linear { start, end },
+            color: None,
+            depth_cue: 0.0,
+        }
+    }
+
+    pub fn exponential(density: f32) -> Self {
+        Self {
+            falloff: FogFalloff::Exponential { density },
+            color: None,
+            depth_cue: 0.0,
+        }
+    }
+
+    pub fn exponential_squared(density: f32) -> Self {
+        Self {
+            falloff: FogFalloff::ExponentialSquared { density },
+            color: None,
+            depth_cue: 0.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_depth_cue(mut self, depth_cue: f32) -> Self {
+        self.depth_cue = depth_cue.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Share of the fog color at `distance`, between 0 and 1, as computed by the shader
+    pub fn amount(&self, distance: f32) -> f32 {
+        match self.falloff {
+            FogFalloff::Linear { start, end } => ((distance - start) / (end - start).max(1.0e-4)).clamp(0.0, 1.0),
+            FogFalloff::Exponential { density } => 1.0 - (-density * distance).exp(),
+            FogFalloff::ExponentialSquared { density } => 1.0 - (-(density * distance).powi(2)).exp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    /// Vertical field of view in radians
+    Perspective { fov: f32 },
+    /// Height of the view volume in world units
+    Orthographic { height: f32 },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective { fov: Camera3d::DEFAULT_FOV }
+    }
 }
 
 pub struct Camera3d {
     transform: Mat4,
-    projection: Mat4,
+    projection_mode: Projection,
+    near: f32,
+    far: f32,
+    /// Follows the viewport and the image resolution when `None`
+    aspect: Option<f32>,
     viewport: Rect2d,
     background: Color,
+    fog: Option<Fog>,
+    sky: Option<Sky>,
+    target: Option<Texture>,
 }
 
 impl Camera for Camera3d {
-    fn view_proj(&self) -> Mat4 {
-        self.projection * self.transform.inverse()
+    fn view_proj(&self, resolution: &Resolution) -> Mat4 {
+        self.projection_matrix(resolution) * self.transform.inverse()
+    }
+
+    fn world_transform(&self, _resolution: &Resolution) -> Mat4 {
+        self.transform
     }
 
     fn mode(&self) -> Mode {
@@ -48,32 +153,86 @@ impl Camera for Camera3d {
     fn background(&self) -> Color {
         self.background
     }
+
+    fn fog(&self) -> Option<Fog> {
+        self.fog
+    }
+
+    fn sky(&self) -> Option<&Sky> {
+        self.sky.as_ref()
+    }
+
+    fn target(&self) -> Option<&Texture> {
+        self.target.as_ref()
+    }
 }
 
 impl Camera3d {
-    pub fn new() -> Self {
-        let transform = Mat4::from_translation(vec3(0.0, 0.0, 5.0));
-        let projection = Mat4::perspective_rh_gl(PI / 4.0, IMAGE_RATIO_XY, 0.01, 100.0);
+    pub const DEFAULT_FOV: f32 = PI / 4.0;
+    pub const DEFAULT_NEAR: f32 = 0.01;
+    pub const DEFAULT_FAR: f32 = 100.0;
+    /// Pitch looking down the diagonal of a cube, as in isometric pixel art
+    pub const ISOMETRIC_PITCH: f32 = -0.615_479_7;
 
+    pub fn new() -> Self {
         Self {
-            transform,
-            projection,
+            transform: Mat4::from_translation(vec3(0.0, 0.0, 5.0)),
+            projection_mode: Default::default(),
+            near: Self::DEFAULT_NEAR,
+            far: Self::DEFAULT_FAR,
+            aspect: None,
             viewport: Rect2d {
                 position: vec2(0.0, 0.0),
                 size: vec2(1.0, 1.0)
             },
             background: Color::black(),
+            fog: None,
+            sky: None,
+            target: None,
         }
     }
 
     pub fn with_viewport(mut self, viewport: &Rect2d) -> Self {
-        self.viewport = *viewport;
-        self.projection = Mat4::perspective_rh_gl(
-            PI / 4.0, 
-            (viewport.size.x / viewport.size.y) * IMAGE_RATIO_XY, 
-            0.01, 
-            100.0
+        self.set_viewport(viewport);
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.set_projection(projection);
+        self
+    }
+
+    pub fn with_fov(mut self, fov: f32) -> Self {
+        self.set_fov(fov);
+        self
+    }
+
+    pub fn with_clip_planes(mut self, near: f32, far: f32) -> Self {
+        self.set_clip_planes(near, far);
+        self
+    }
+
+    pub fn with_orthographic(mut self, height: f32) -> Self {
+        self.set_projection(Projection::Orthographic { height });
+        self
+    }
+
+    /// Orthographic camera looking at `target` from `distance` along the classic isometric angles
+    pub fn with_isometric(mut self, target: Vec3, height: f32, distance: f32) -> Self {
+        let rotation = Quat::from_euler(EulerRot::YXZ, PI / 4.0, Self::ISOMETRIC_PITCH, 0.0);
+        self.transform = Mat4::from_rotation_translation(
+            rotation,
+            target + rotation * vec3(0.0, 0.0, distance)
         );
+        self.set_projection(Projection::Orthographic { height });
+        if self.far < distance * 2.0 {
+            self.set_clip_planes(self.near, distance * 2.0);
+        }
+        self
+    }
+
+    pub fn with_look_at(mut self, target: Vec3, up: Vec3) -> Self {
+        self.look_at(target, up);
         self
     }
 
@@ -81,6 +240,23 @@ impl Camera3d {
         self.background = background;
         self
     }
+
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Drawn behind the geometry instead of the solid background
+    pub fn with_sky(mut self, sky: Sky) -> Self {
+        self.sky = Some(sky);
+        self
+    }
+
+    /// Draws into a texture created with `Texture::render_target`
+    pub fn with_target(mut self, target: &Texture) -> Self {
+        self.target = Some(target.clone());
+        self
+    }
     
     pub fn with_transform(mut self, transform: &Mat4) -> Self {
         self.transform = *transform;
@@ -117,8 +293,46 @@ impl Camera3d {
         self
     }
 
+    /// Also resets the aspect ratio to the one of the viewport
     pub fn set_viewport(&mut self, viewport: &Rect2d) -> &mut Self {
         self.viewport = *viewport;
+        self.aspect = None;
+        self
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) -> &mut Self {
+        self.projection_mode = projection;
+        self
+    }
+
+    /// Switches to a perspective projection if needed
+    pub fn set_fov(&mut self, fov: f32) -> &mut Self {
+        self.set_projection(Projection::Perspective { fov })
+    }
+
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) -> &mut Self {
+        self.near = near;
+        self.far = far;
+        self
+    }
+
+    /// Overrides the aspect ratio until the next viewport change
+    pub fn set_aspect(&mut self, aspect: f32) -> &mut Self {
+        self.aspect = Some(aspect);
+        self
+    }
+
+    pub fn set_position(&mut self, position: Vec3) -> &mut Self {
+        self.transform.w_axis = position.extend(1.0);
+        self
+    }
+
+    /// Turns the camera towards `target` without moving it
+    pub fn look_at(&mut self, target: Vec3, up: Vec3) -> &mut Self {
+        let position = self.position();
+        if (target - position).length_squared() > f32::EPSILON {
+            self.transform = Mat4::look_at_rh(position, target, up).inverse();
+        }
         self
     }
 
@@ -147,21 +361,118 @@ impl Camera3d {
         self
     }
 
+    pub fn transform(&self) -> &Mat4 {
+        &self.transform
+    }
+
     pub fn position(&self) -> Vec3 {
         self.transform.w_axis.xyz()
     }
+
+    pub fn forward(&self) -> Vec3 {
+        -self.transform.z_axis.xyz().normalize_or_zero()
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.transform.x_axis.xyz().normalize_or_zero()
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.transform.y_axis.xyz().normalize_or_zero()
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection_mode
+    }
+
+    pub fn set_fog(&mut self, fog: Option<Fog>) -> &mut Self {
+        self.fog = fog;
+        self
+    }
+
+    pub fn fog(&self) -> Option<Fog> {
+        self.fog
+    }
+
+    pub fn set_sky(&mut self, sky: Option<Sky>) -> &mut Self {
+        self.sky = sky;
+        self
+    }
+
+    pub fn sky(&self) -> Option<&Sky> {
+        self.sky.as_ref()
+    }
+
+    pub fn set_target(&mut self, target: Option<Texture>) -> &mut Self {
+        self.target = target;
+        self
+    }
+
+    pub fn target(&self) -> Option<&Texture> {
+        self.target.as_ref()
+    }
+
+    pub fn projection_matrix(&self, resolution: &Resolution) -> Mat4 {
+        let aspect = self.aspect(resolution);
+        match self.projection_mode {
+            Projection::Perspective { fov } => Mat4::perspective_rh_gl(fov, aspect, self.near, self.far),
+            Projection::Orthographic { height } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect;
+                Mat4::orthographic_rh_gl(-half_width, half_width, -half_height, half_height, self.near, self.far)
+            },
+        }
+    }
+
+    /// Vertical field of view, `None` for orthographic cameras
+    pub fn fov(&self) -> Option<f32> {
+        match self.projection_mode {
+            Projection::Perspective { fov } => Some(fov),
+            Projection::Orthographic { .. } => None,
+        }
+    }
+
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    /// Width over height of the viewport on the display
+    pub fn aspect(&self, resolution: &Resolution) -> f32 {
+        self.aspect.unwrap_or_else(|| {
+            let aspect = match &self.target {
+                Some(target) => target.width() as f32 / target.height() as f32,
+                None => resolution.aspect(),
+            };
+            (self.viewport.size.x / self.viewport.size.y) * aspect
+        })
+    }
 }
 
 pub struct Camera2d {
-    transform: Mat4,
-    projection: Mat4,
     viewport: Rect2d,
     background: Color,
+    /// Centered on the image or the target when `None`, following their size, not clamped to the bounds
+    position: Option<Vec2>,
+    zoom: f32,
+    rotation: f32,
+    bounds: Option<Rect2d>,
+    deadzone: Vec2,
+    follow_smoothing: f32,
+    target: Option<Texture>,
 }
 
 impl Camera for Camera2d {
-    fn view_proj(&self) -> Mat4 {
-        self.projection * self.transform
+    fn view_proj(&self, resolution: &Resolution) -> Mat4 {
+        let size = self.viewport_size(resolution);
+        Mat4::orthographic_rh_gl(0.0, size.x, size.y, 0.0, -1.0, 1.0) * self.view_matrix(resolution)
+    }
+
+    fn world_transform(&self, resolution: &Resolution) -> Mat4 {
+        self.view_matrix(resolution).inverse()
     }
 
     fn mode(&self) -> Mode {
@@ -175,33 +486,41 @@ impl Camera for Camera2d {
     fn background(&self) -> Color {
         self.background
     }
+
+    fn fog(&self) -> Option<Fog> {
+        None
+    }
+
+    fn sky(&self) -> Option<&Sky> {
+        None
+    }
+
+    fn target(&self) -> Option<&Texture> {
+        self.target.as_ref()
+    }
 }
 
 impl Camera2d {
     pub fn new() -> Self {
-        let transform = Mat4::IDENTITY;
-        let projection = glam::Mat4::orthographic_rh_gl(
-            0.0, 
-            IMAGE_RES.x as f32, 
-            IMAGE_RES.y as f32, 
-            0.0, 
-            -1.0, 
-            1.0
-        );
-
         Self {
-            transform,
-            projection,
             viewport: Rect2d {
                 position: vec2(0.0, 0.0),
                 size: vec2(1.0, 1.0)
             },
             background: Color::black(),
+            position: None,
+            zoom: 1.0,
+            rotation: 0.0,
+            bounds: None,
+            deadzone: Vec2::ZERO,
+            follow_smoothing: 0.0,
+            target: None,
         }
     }
 
+    /// One world unit covers one pixel of the viewport at zoom 1
     pub fn with_viewport(mut self, viewport: &Rect2d) -> Self {
-        self.viewport = *viewport;
+        self.set_viewport(viewport);
         self
     }
 
@@ -209,6 +528,193 @@ impl Camera2d {
         self.background = background;
         self
     }
+
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.set_position(position);
+        self
+    }
+
+    /// Draws into a texture created with `Texture::render_target`, centered on it
+    pub fn with_target(mut self, target: &Texture) -> Self {
+        self.target = Some(target.clone());
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.set_zoom(zoom);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.set_rotation(rotation);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: &Rect2d) -> Self {
+        self.set_bounds(Some(*bounds));
+        self
+    }
+
+    /// Half size of the area around the center in which a followed target can move
+    /// without moving the camera
+    pub fn with_deadzone(mut self, deadzone: Vec2) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+
+    /// How fast the camera catches up with a followed target, 0 snaps instantly
+    pub fn with_follow_smoothing(mut self, follow_smoothing: f32) -> Self {
+        self.follow_smoothing = follow_smoothing;
+        self
+    }
+
+    pub fn set_viewport(&mut self, viewport: &Rect2d) -> &mut Self {
+        self.viewport = *viewport;
+        self
+    }
+
+    /// Sets the world position shown at the center of the viewport
+    pub fn set_position(&mut self, position: Vec2) -> &mut Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn pan(&mut self, delta: Vec2, resolution: &Resolution) -> &mut Self {
+        self.set_position(self.position(resolution) + delta)
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) -> &mut Self {
+        self.zoom = zoom.max(f32::EPSILON);
+        self
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) -> &mut Self {
+        self.set_zoom(self.zoom * factor)
+    }
+
+    pub fn set_rotation(&mut self, rotation: f32) -> &mut Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn rotate(&mut self, angle: f32) -> &mut Self {
+        self.set_rotation(self.rotation + angle)
+    }
+
+    /// Keeps the visible area inside `bounds`
+    pub fn set_bounds(&mut self, bounds: Option<Rect2d>) -> &mut Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Moves the camera so that `target` stays inside the deadzone
+    pub fn follow(&mut self, target: Vec2, dt: f32, resolution: &Resolution) -> &mut Self {
+        let offset = target - self.position(resolution);
+        let excess = offset - offset.clamp(-self.deadzone, self.deadzone);
+        let factor = if self.follow_smoothing <= 0.0 {
+            1.0
+        } else {
+            1.0 - (-self.follow_smoothing * dt).exp()
+        };
+        self.pan(excess * factor, resolution)
+    }
+
+    /// World position shown at the center of the viewport, kept inside the bounds
+    pub fn position(&self, resolution: &Resolution) -> Vec2 {
+        let position = self.position.unwrap_or(surface_size(self.target.as_ref(), resolution) / 2.0);
+        self.clamp_to_bounds(position, resolution)
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn bounds(&self) -> Option<&Rect2d> {
+        self.bounds.as_ref()
+    }
+
+    pub fn set_target(&mut self, target: Option<Texture>) -> &mut Self {
+        self.target = target;
+        self
+    }
+
+    pub fn target(&self) -> Option<&Texture> {
+        self.target.as_ref()
+    }
+
+    /// Size of the viewport in image pixels
+    pub fn viewport_size(&self, resolution: &Resolution) -> Vec2 {
+        self.viewport.size * surface_size(self.target.as_ref(), resolution)
+    }
+
+    /// Top left corner of the viewport in image pixels
+    pub fn viewport_origin(&self, resolution: &Resolution) -> Vec2 {
+        vec2(
+            self.viewport.position.x,
+            1.0 - self.viewport.position.y - self.viewport.size.y,
+        ) * surface_size(self.target.as_ref(), resolution)
+    }
+
+    /// Size of the visible area in world units, ignoring the rotation
+    pub fn view_size(&self, resolution: &Resolution) -> Vec2 {
+        self.viewport_size(resolution) / self.zoom
+    }
+
+    /// Converts a position in image pixels to world coordinates
+    pub fn image_to_world(&self, image_pos: Vec2, resolution: &Resolution) -> Vec2 {
+        let local = image_pos - self.viewport_origin(resolution);
+        self.view_matrix(resolution).inverse().transform_point3(local.extend(0.0)).truncate()
+    }
+
+    /// Converts world coordinates to a position in image pixels
+    pub fn world_to_image(&self, world_pos: Vec2, resolution: &Resolution) -> Vec2 {
+        self.view_matrix(resolution).transform_point3(world_pos.extend(0.0)).truncate() + self.viewport_origin(resolution)
+    }
+
+    /// Converts a position in window pixels, such as the mouse position, to world coordinates
+    pub fn screen_to_world(&self, screen_pos: Vec2, screen_size: Vec2, resolution: &Resolution) -> Vec2 {
+        self.image_to_world(screen_to_image(screen_pos, screen_size, resolution), resolution)
+    }
+
+    /// Converts world coordinates to a position in window pixels
+    pub fn world_to_screen(&self, world_pos: Vec2, screen_size: Vec2, resolution: &Resolution) -> Vec2 {
+        image_to_screen(self.world_to_image(world_pos, resolution), screen_size, resolution)
+    }
+
+    /// Clamped when read, the visible area depends on the resolution
+    fn clamp_to_bounds(&self, position: Vec2, resolution: &Resolution) -> Vec2 {
+        let Some(bounds) = self.bounds else {
+            return position;
+        };
+
+        // extent of the rotated visible area
+        let (sin, cos) = self.rotation.sin_cos();
+        let size = self.view_size(resolution);
+        let half_extent = vec2(
+            size.x * cos.abs() + size.y * sin.abs(),
+            size.x * sin.abs() + size.y * cos.abs(),
+        ) / 2.0;
+
+        let min = bounds.position + half_extent;
+        let max = bounds.position + bounds.size - half_extent;
+        let center = bounds.position + bounds.size / 2.0;
+        vec2(
+            if min.x <= max.x { position.x.clamp(min.x, max.x) } else { center.x },
+            if min.y <= max.y { position.y.clamp(min.y, max.y) } else { center.y },
+        )
+    }
+
+    /// World to viewport pixels
+    fn view_matrix(&self, resolution: &Resolution) -> Mat4 {
+        Mat4::from_translation((self.viewport_size(resolution) / 2.0).extend(0.0))
+            * Mat4::from_scale(vec3(self.zoom, self.zoom, 1.0))
+            * Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_translation((-self.position(resolution)).extend(0.0))
+    }
 }
 
 pub struct Graphics<'a> {
@@ -217,20 +723,120 @@ pub struct Graphics<'a> {
 
 impl<'a> Graphics<'a> {
     pub fn set_camera(&mut self, camera: &dyn Camera) -> &mut Self {
-        self.data.view_proj = camera.view_proj();
+        // the queued geometry belongs to the previous camera
+        self.flush_transparent();
+        self.flush_debug_lines();
+        self.data.target = camera.target().cloned();
+        let view_proj = camera.view_proj(&self.data.resolution);
+        self.data.view_proj = match self.data.target {
+            // render targets are stored bottom up, they are drawn upside down to be sampled with top left texture coordinates
+            Some(_) => Mat4::from_scale(vec3(1.0, -1.0, 1.0)) * view_proj,
+            None => view_proj,
+        };
+        self.data.camera_transform = camera.world_transform(&self.data.resolution);
         self.data.mode = camera.mode();
         self.data.viewport = *camera.viewport();
         self.data.background = camera.background();
+        self.data.fog = camera.fog().map(|fog| Fog {
+            color: Some(fog.color.unwrap_or(camera.background())),
+            ..fog
+        });
+        if let Some(sky) = camera.sky() {
+            self.data.skies.push(SkyDraw {
+                before: self.data.draw_calls_count,
+                sky: sky.clone(),
+                inverse_view_proj: self.data.view_proj.inverse(),
+                viewport: self.data.viewport,
+                target: self.data.target.clone(),
+            });
+        }
+        if self.data.mode == Mode::Mode3d {
+            self.queue_timed_lines();
+        }
+        self
+    }
+
+    /// Size of the image and shape of its pixels, the cameras and the picking helpers take it
+    pub fn resolution(&self) -> Resolution {
+        self.data.resolution
+    }
+
+    /// Changes the resolution of the image, the renderer resizes it on the next frame.
+    /// Cameras already set this frame keep the previous one.
+    pub fn set_resolution(&mut self, resolution: Resolution) -> &mut Self {
+        assert!(resolution.size.x > 0 && resolution.size.y > 0, "the image can't be empty");
+        self.data.resolution = resolution;
+        self
+    }
+
+    /// Debug view of the following draws, kept for the next frames
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) -> &mut Self {
+        self.data.render_mode = render_mode;
+        self
+    }
+
+    /// Mode of the frame, or of the material when the frame is shaded
+    fn render_mode(&self, material: &Material) -> RenderMode {
+        match self.data.render_mode {
+            RenderMode::Shaded => material.render_mode,
+            render_mode => render_mode,
+        }
+    }
+
+    /// Sets the directional light of the frame, and its shadow map if it has one
+    pub fn set_light(&mut self, light: &Light) -> &mut Self {
+        self.data.light = *light;
         self
     }
 
     pub fn draw_object(&mut self, object: &Object) -> &mut Self {
-        self.new_draw_call(
-            object.vertices(),
-            object.indices(),
-            object.transform(),
-            Primitive::Triangles,
-        )
+        let material = object.material();
+
+        let tinted: Option<Vec<Vertex>> = (material.color != Color::white()).then(|| {
+            object.vertices()
+                .iter()
+                .map(|vertex| Vertex {
+                    color: (Color::from(vertex.color) * material.color).as_array(),
+                    ..vertex.clone()
+                })
+                .collect()
+        });
+        let vertices = tinted.as_ref().unwrap_or(object.vertices());
+
+        let edges = material.wireframe.then(|| wireframe_indices(object.indices()));
+        let indices = edges.as_ref().unwrap_or(object.indices());
+        let primitive = if material.wireframe { Primitive::Lines } else { Primitive::Triangles };
+        let transparent = material.is_transparent();
+        self.draw_geometry(vertices, indices, object.transform(), primitive, material, transparent);
+
+        if !material.wireframe && self.render_mode(material) == RenderMode::Wireframe {
+            let vertices = object.vertices()
+                .iter()
+                .map(|vertex| Vertex {
+                    color: Color::green().as_array(),
+                    ..vertex.clone()
+                })
+                .collect();
+            let indices = wireframe_indices(object.indices());
+            self.draw_geometry(&vertices, &indices, object.transform(), Primitive::Lines, &Material::unlit(), transparent);
+        }
+        self
+    }
+
+    /// Draws every object of the scene through its active camera, if any.
+    /// `Scene::update` must have been called for the world transforms to be up to date.
+    pub fn draw_scene(&mut self, scene: &Scene) -> &mut Self {
+        if let Some(camera) = scene.active_camera() {
+            self.set_camera(camera);
+        }
+
+        scene.walk(|_, node| {
+            if let NodeContent::Object(object) = node.content() {
+                self.draw_object(object);
+            }
+        });
+
+        self
     }
 
     pub fn draw_line(&mut self, p1: Vec3, p2: Vec3, color: Color) -> &mut Self {
@@ -240,11 +846,13 @@ impl<'a> Graphics<'a> {
                     position: p1.to_array(),
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [0.0, 0.0],
                 },
                 Vertex {
                     position: p2.to_array(),
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [0.0, 0.0],
                 },
             ], 
             &vec![
@@ -252,6 +860,7 @@ impl<'a> Graphics<'a> {
             ], 
             &Mat4::IDENTITY,
             Primitive::Lines,
+            &Material::default(),
         )
     }
 
@@ -262,21 +871,25 @@ impl<'a> Graphics<'a> {
                     position: [position.x, position.y, 0.0],
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [0.0, 0.0],
                 },
                 Vertex {
                     position: [position.x + size.x, position.y, 0.0],
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [1.0, 0.0],
                 },
                 Vertex {
                     position: [position.x, position.y + size.y, 0.0],
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [0.0, 1.0],
                 },
                 Vertex {
                     position: [position.x + size.x, position.y + size.y, 0.0],
                     color: color.as_array(),
                     normal: [0.0, 0.0, 0.0],
+                    uv: [1.0, 1.0],
                 },
             ], 
             &vec![
@@ -284,16 +897,155 @@ impl<'a> Graphics<'a> {
             ], 
             &Mat4::IDENTITY,
             Primitive::Triangles,
+            &Material::default(),
         )
     }
 
-    fn new_draw_call(
+    /// Draws the living particles in a single draw call, as quads facing the camera in 3D
+    /// and as squares in 2D
+    pub fn draw_particles(&mut self, emitter: &ParticleEmitter) -> &mut Self {
+        let camera = self.data.camera_transform;
+        let mut particles: Vec<&Particle> = emitter.particles().iter().collect();
+        if self.data.mode == Mode::Mode3d {
+            // back to front, so they blend over each other
+            let eye = camera.w_axis.xyz();
+            particles.sort_by(|a, b| {
+                (b.position - eye).length_squared().total_cmp(&(a.position - eye).length_squared())
+            });
+        }
+        let (right, up) = camera_basis(&camera, self.data.mode);
+
+        if particles.is_empty() {
+            return self;
+        }
+
+        let mut vertices = Vec::with_capacity(particles.len() * 4);
+        let mut indices = Vec::with_capacity(particles.len() * 6);
+        for particle in particles {
+            let half_size = emitter.size(particle) * 0.5;
+            let (right, up) = (right * half_size, up * half_size);
+            let color = emitter.color(particle).as_array();
+            let first = vertices.len() as i32;
+            for (corner, uv) in [(-right + up, [0.0, 0.0]), (right + up, [1.0, 0.0]), (-right - up, [0.0, 1.0]), (right - up, [1.0, 1.0])] {
+                vertices.push(Vertex {
+                    position: (particle.position + corner).to_array(),
+                    color,
+                    normal: [0.0, 0.0, 0.0],
+                    uv,
+                });
+            }
+            indices.extend_from_slice(&[first, first + 1, first + 2, first + 1, first + 3, first + 2]);
+        }
+
+        let material = Material::unlit().with_blend(BlendMode::Alpha);
+        self.queue_transparent(emitter.position(), vertices, indices, &Mat4::IDENTITY, Primitive::Triangles, material)
+    }
+
+    /// Draws a quad facing the camera, sorted with the other transparent geometry
+    pub fn draw_billboard(&mut self, billboard: &Billboard) -> &mut Self {
+        let vertices = billboard.vertices(&self.data.camera_transform, self.data.mode);
+        let mut material = Material::unlit().with_blend(BlendMode::Alpha);
+        material.texture = billboard.texture.clone();
+        self.queue_transparent(billboard.position, vertices, Billboard::indices(), &Mat4::IDENTITY, Primitive::Triangles, material)
+    }
+
+    /// Darkens a disc on the ground below an object, a cheap alternative to shadow maps
+    pub fn draw_blob_shadow(&mut self, ground: Vec3, radius: f32, opacity: f32) -> &mut Self {
+        const SEGMENTS: i32 = 12;
+        // slightly above the ground to avoid z-fighting
+        let center = ground + Vec3::Y * 0.01;
+        let mut vertices = vec![Vertex {
+            position: center.to_array(),
+            color: [0.0, 0.0, 0.0, opacity],
+            normal: [0.0, 0.0, 0.0],
+            uv: [0.5, 0.5],
+        }];
+        let mut indices = Vec::with_capacity(SEGMENTS as usize * 3);
+        for i in 0..SEGMENTS {
+            let angle = i as f32 / SEGMENTS as f32 * 2.0 * PI;
+            vertices.push(Vertex {
+                position: (center + vec3(angle.cos(), 0.0, angle.sin()) * radius).to_array(),
+                color: [0.0, 0.0, 0.0, 0.0],
+                normal: [0.0, 0.0, 0.0],
+                uv: [0.5, 0.5],
+            });
+            indices.extend_from_slice(&[0, 1 + i, 1 + (i + 1) % SEGMENTS]);
+        }
+        let material = Material::unlit().with_blend(BlendMode::Alpha);
+        self.queue_transparent(center, vertices, indices, &Mat4::IDENTITY, Primitive::Triangles, material)
+    }
+
+    /// Draws now or after the opaque geometry, sorted by the distance of the transform origin
+    fn draw_geometry(
+        &mut self,
+        vertices: &Vec<Vertex>,
+        indices: &Vec<i32>,
+        transform: &Mat4,
+        primitive: Primitive,
+        material: &Material,
+        transparent: bool,
+    ) -> &mut Self {
+        if transparent {
+            self.queue_transparent(
+                transform.w_axis.xyz(),
+                vertices.clone(),
+                indices.clone(),
+                transform,
+                primitive,
+                material.clone(),
+            )
+        } else {
+            self.new_draw_call(vertices, indices, transform, primitive, material)
+        }
+    }
+
+    fn queue_transparent(
+        &mut self,
+        position: Vec3,
+        vertices: Vec<Vertex>,
+        indices: Vec<i32>,
+        transform: &Mat4,
+        primitive: Primitive,
+        material: Material,
+    ) -> &mut Self {
+        let distance = match self.data.mode {
+            Mode::Mode3d => (position - self.data.camera_transform.w_axis.xyz()).length(),
+            Mode::Mode2d => 0.0,
+        };
+        self.data.transparent.push(TransparentDraw {
+            distance,
+            vertices,
+            indices,
+            transform: *transform,
+            primitive,
+            material,
+        });
+        self
+    }
+
+    /// Draws the queued transparent geometry from back to front, done when the camera changes
+    /// and at the end of the frame
+    pub fn flush_transparent(&mut self) -> &mut Self {
+        let mut queue = std::mem::take(&mut self.data.transparent);
+        // stable, so 2D geometry keeps its drawing order
+        queue.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+        for draw in queue.drain(..) {
+            self.new_draw_call(&draw.vertices, &draw.indices, &draw.transform, draw.primitive, &draw.material);
+        }
+        // keep the allocation
+        self.data.transparent = queue;
+        self
+    }
+
+    pub(crate) fn new_draw_call(
         &mut self, 
         vertices: &Vec<Vertex>, 
         indices: &Vec<i32>, 
         transform: &Mat4, 
         primitive: Primitive, 
+        material: &Material,
     ) -> &mut Self {
+        let render_mode = self.render_mode(material);
         let previous_dc = if self.data.draw_calls_count == 0 {
             None
         } else {
@@ -305,7 +1057,11 @@ impl<'a> Graphics<'a> {
             draw_call.view_proj != self.data.view_proj ||
             draw_call.primitive != primitive ||
             draw_call.mode != self.data.mode ||
-            draw_call.viewport != self.data.viewport
+            draw_call.viewport != self.data.viewport ||
+            draw_call.fog != self.data.fog ||
+            !draw_call.material.batches_with(material) ||
+            draw_call.render_mode != render_mode ||
+            draw_call.target != self.data.target
         }) {
             // start a new draw call
             if self.data.draw_calls.len() <= self.data.draw_calls_count {
@@ -320,6 +1076,11 @@ impl<'a> Graphics<'a> {
                         mode:  self.data.mode,
                         viewport: self.data.viewport,
                         background: self.data.background,
+                        camera_position: self.data.camera_transform.w_axis.xyz(),
+                        fog: self.data.fog,
+                        material: material.clone(),
+                        render_mode,
+                        target: self.data.target.clone(),
                     }
                 );
             } else {
@@ -332,6 +1093,11 @@ impl<'a> Graphics<'a> {
                 self.data.draw_calls[self.data.draw_calls_count].mode = self.data.mode;
                 self.data.draw_calls[self.data.draw_calls_count].viewport = self.data.viewport;
                 self.data.draw_calls[self.data.draw_calls_count].background = self.data.background;
+                self.data.draw_calls[self.data.draw_calls_count].camera_position = self.data.camera_transform.w_axis.xyz();
+                self.data.draw_calls[self.data.draw_calls_count].fog = self.data.fog;
+                self.data.draw_calls[self.data.draw_calls_count].material = material.clone();
+                self.data.draw_calls[self.data.draw_calls_count].render_mode = render_mode;
+                self.data.draw_calls[self.data.draw_calls_count].target = self.data.target.clone();
             }
     
             self.data.draw_calls_count += 1;
@@ -353,4 +1119,130 @@ impl<'a> Graphics<'a> {
         
         self
     }
-}
\ No newline at end of file
+}
+
+/// Pairs of indices for the edges of each triangle
+fn wireframe_indices(indices: &[i32]) -> Vec<i32> {
+    indices
+        .chunks_exact(3)
+        .flat_map(|triangle| [triangle[0], triangle[1], triangle[1], triangle[2], triangle[2], triangle[0]])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_changes_rebuild_the_projection() {
+        let mut camera = Camera3d::new().with_fov(1.0).with_clip_planes(0.1, 10.0);
+        camera.set_viewport(&Rect2d { position: vec2(0.0, 0.0), size: vec2(0.5, 1.0) });
+        let resolution = Resolution::DEFAULT;
+        let aspect = resolution.aspect() / 2.0;
+        assert!((camera.aspect(&resolution) - aspect).abs() < 1.0e-5);
+        assert!(camera.projection_matrix(&resolution).abs_diff_eq(
+            Mat4::perspective_rh_gl(1.0, aspect, 0.1, 10.0),
+            1.0e-5
+        ));
+    }
+
+    #[test]
+    fn look_at_orients_the_basis() {
+        let mut camera = Camera3d::new();
+        camera.set_position(vec3(0.0, 0.0, 5.0)).look_at(vec3(5.0, 0.0, 5.0), Vec3::Y);
+        assert!(camera.forward().abs_diff_eq(Vec3::X, 1.0e-5));
+        assert!(camera.right().abs_diff_eq(Vec3::Z, 1.0e-5));
+        assert!(camera.up().abs_diff_eq(Vec3::Y, 1.0e-5));
+        assert!(camera.position().abs_diff_eq(vec3(0.0, 0.0, 5.0), 1.0e-5));
+    }
+
+    #[test]
+    fn isometric_camera_looks_at_its_target() {
+        let camera = Camera3d::new().with_isometric(vec3(1.0, 0.0, 1.0), 10.0, 20.0);
+        assert_eq!(camera.projection(), Projection::Orthographic { height: 10.0 });
+        let to_target = (vec3(1.0, 0.0, 1.0) - camera.position()).normalize();
+        assert!(camera.forward().abs_diff_eq(to_target, 1.0e-5));
+        assert!((camera.forward().y + 1.0 / 3.0_f32.sqrt()).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn cameras_draw_into_their_target() {
+        let target = Texture::render_target(64, 32);
+        let camera = Camera3d::new().with_target(&target);
+        assert!((camera.aspect(&Resolution::DEFAULT) - 2.0).abs() < 1.0e-5);
+
+        let mut data = RendererData::new();
+        let mut graphics = Graphics { data: &mut data };
+        let cube = Object::new_cube(Color::red());
+        graphics
+            .set_camera(&camera)
+            .draw_object(&cube)
+            .set_camera(&Camera3d::new())
+            .draw_object(&cube);
+        assert_eq!(data.draw_calls_count, 2);
+        assert_eq!(data.draw_calls[0].target.as_ref(), Some(&target));
+        assert_eq!(data.draw_calls[1].target, None);
+    }
+
+    #[test]
+    fn wireframe_edges() {
+        assert_eq!(wireframe_indices(&[0, 1, 2, 2, 1, 3]), vec![0, 1, 1, 2, 2, 0, 2, 1, 1, 3, 3, 2]);
+    }
+
+    #[test]
+    fn fog_falloffs() {
+        let linear = Fog::linear(10.0, 30.0);
+        assert_eq!(linear.amount(5.0), 0.0);
+        assert!((linear.amount(20.0) - 0.5).abs() < 1.0e-5);
+        assert_eq!(linear.amount(50.0), 1.0);
+
+        let exponential = Fog::exponential(0.1);
+        let squared = Fog::exponential_squared(0.1);
+        assert_eq!(exponential.amount(0.0), 0.0);
+        assert!((exponential.amount(10.0) - (1.0 - (-1.0_f32).exp())).abs() < 1.0e-5);
+        // the squared falloff keeps the foreground clearer
+        assert!(squared.amount(5.0) < exponential.amount(5.0));
+        assert!(squared.amount(30.0) > exponential.amount(30.0));
+    }
+
+    #[test]
+    fn default_camera_2d_maps_world_to_image_pixels() {
+        let camera = Camera2d::new();
+        let resolution = Resolution::DEFAULT;
+        assert!(camera.view_proj(&resolution).abs_diff_eq(
+            Mat4::orthographic_rh_gl(0.0, 320.0, 200.0, 0.0, -1.0, 1.0),
+            1.0e-5
+        ));
+        assert!(camera.image_to_world(vec2(12.0, 34.0), &resolution).abs_diff_eq(vec2(12.0, 34.0), 1.0e-4));
+    }
+
+    #[test]
+    fn screen_to_world_accounts_for_letterboxing() {
+        let camera = Camera2d::new().with_zoom(2.0).with_rotation(0.5).with_position(vec2(40.0, 30.0));
+        // window twice as wide as the image ratio, the image is centered with bars on the sides
+        let screen_size = vec2(1280.0, 400.0);
+        let resolution = Resolution::DEFAULT;
+        assert!(camera.screen_to_world(vec2(640.0, 200.0), screen_size, &resolution).abs_diff_eq(vec2(40.0, 30.0), 1.0e-4));
+
+        let world = vec2(55.0, 20.0);
+        let screen = camera.world_to_screen(world, screen_size, &resolution);
+        assert!(camera.screen_to_world(screen, screen_size, &resolution).abs_diff_eq(world, 1.0e-3));
+    }
+
+    #[test]
+    fn follow_respects_deadzone_and_bounds() {
+        let mut camera = Camera2d::new()
+            .with_position(vec2(0.0, 0.0))
+            .with_deadzone(vec2(10.0, 10.0))
+            .with_bounds(&Rect2d { position: vec2(-500.0, -500.0), size: vec2(1000.0, 1000.0) });
+        let resolution = Resolution::DEFAULT;
+        assert_eq!(camera.position(&resolution), vec2(0.0, 0.0));
+
+        camera.follow(vec2(5.0, 0.0), 0.016, &resolution);
+        assert_eq!(camera.position(&resolution), vec2(0.0, 0.0));
+        camera.follow(vec2(30.0, -4.0), 0.016, &resolution);
+        assert_eq!(camera.position(&resolution), vec2(20.0, 0.0));
+        camera.follow(vec2(1000.0, 0.0), 0.016, &resolution);
+        assert_eq!(camera.position(&resolution), vec2(340.0, 0.0));
+    }
+}
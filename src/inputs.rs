@@ -4,21 +4,28 @@ use glam::Vec2;
 
 pub struct Inputs {
     keys: HashMap<miniquad::KeyCode, bool>,
+    mouse_buttons: HashMap<miniquad::MouseButton, bool>,
     mouse_position: Vec2,
     mouse_delta: Vec2,
+    mouse_wheel: Vec2,
+    screen_size: Vec2,
 }
 
 impl Inputs {
     pub fn new() -> Self {
         Self {
             keys: HashMap::default(),
+            mouse_buttons: HashMap::default(),
             mouse_position: Vec2::ZERO,
             mouse_delta: Vec2::ZERO,
+            mouse_wheel: Vec2::ZERO,
+            screen_size: Vec2::ONE,
         }
     }
 
     pub fn reset(&mut self) {
         self.mouse_delta = Vec2::ZERO;
+        self.mouse_wheel = Vec2::ZERO;
     }
 
     pub fn mouse_position(&self) -> Vec2 {
@@ -29,6 +36,16 @@ impl Inputs {
         self.mouse_delta
     }
 
+    pub fn mouse_wheel(&self) -> Vec2 {
+        self.mouse_wheel
+    }
+
+    /// Size of the window in pixels, to convert the mouse position with `Camera2d::screen_to_world`
+    /// or `Camera3d::screen_ray`
+    pub fn screen_size(&self) -> Vec2 {
+        self.screen_size
+    }
+
     pub fn key(&self, keycode: miniquad::KeyCode) -> bool {
         match self.keys.get(&keycode) {
             Some(b) => *b,
@@ -36,6 +53,13 @@ impl Inputs {
         }
     }
 
+    pub fn mouse_button(&self, button: miniquad::MouseButton) -> bool {
+        match self.mouse_buttons.get(&button) {
+            Some(b) => *b,
+            None => false,
+        }
+    }
+
     pub fn mouse_motion_event(&mut self, x: f32, y: f32) {
         self.mouse_delta.x = x - self.mouse_position.x;
         self.mouse_delta.y = y - self.mouse_position.y;
@@ -43,6 +67,24 @@ impl Inputs {
         self.mouse_position.y = y;
     }
 
+    pub fn resize_event(&mut self, width: f32, height: f32) {
+        self.screen_size.x = width;
+        self.screen_size.y = height;
+    }
+
+    pub fn mouse_wheel_event(&mut self, dx: f32, dy: f32) {
+        self.mouse_wheel.x += dx;
+        self.mouse_wheel.y += dy;
+    }
+
+    pub fn mouse_button_down_event(&mut self, button: miniquad::MouseButton) {
+        self.mouse_buttons.insert(button, true);
+    }
+
+    pub fn mouse_button_up_event(&mut self, button: miniquad::MouseButton) {
+        self.mouse_buttons.insert(button, false);
+    }
+
     pub fn key_down_event(&mut self, keycode: miniquad::KeyCode) {
         self.keys.insert(keycode, true);
     }
@@ -50,4 +92,4 @@ impl Inputs {
     pub fn key_up_event(&mut self, keycode: miniquad::KeyCode) {
         self.keys.insert(keycode, false);
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,181 @@
-use glam::Vec3;
+use glam::{vec3, Mat4, Vec3};
+use serde::{Deserialize, Serialize};
 
+/// How the shader lights the polygons
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Shading {
+    /// One color per polygon, lit at its first vertex
+    #[default]
+    Flat,
+    /// Lit at the vertices and blended across the polygon
+    Gouraud,
+    /// Diffuse light computed for each pixel
+    Lambert,
+    /// Per pixel diffuse light with specular highlights
+    BlinnPhong { specular: f32, shininess: f32 },
+    /// Vertex colors only
+    Unlit,
+}
+
+/// Shadow map settings of a directional light
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shadow {
+    /// Width and height of the depth texture in texels, low values give big hard-edged texels
+    pub resolution: u32,
+    /// Half size of the square area covered around the light position
+    pub extent: f32,
+    /// Depth of the covered volume along the light direction, centered on the light position
+    pub depth: f32,
+    /// Offset against shadow acne, in the 0 to 1 depth range
+    pub bias: f32,
+    /// Averages 3x3 samples for softer edges
+    pub soft: bool,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            extent: 10.0,
+            depth: 50.0,
+            bias: 0.005,
+            soft: false,
+        }
+    }
+}
+
+impl Shadow {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    pub fn with_area(mut self, extent: f32, depth: f32) -> Self {
+        self.extent = extent;
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_soft(mut self, soft: bool) -> Self {
+        self.soft = soft;
+        self
+    }
+}
+
+/// Directional light, its position is the center of the area receiving shadows
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Light {
     position: Vec3,
-}
\ No newline at end of file
+    direction: Vec3,
+    shadow: Option<Shadow>,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            direction: vec3(-1.0, -1.0, -1.0).normalize(),
+            shadow: None,
+        }
+    }
+}
+
+impl Light {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Direction the light travels in
+    pub fn with_direction(mut self, direction: Vec3) -> Self {
+        self.set_direction(direction);
+        self
+    }
+
+    pub fn with_shadow(mut self, shadow: Shadow) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    pub fn set_position(&mut self, position: Vec3) -> &mut Self {
+        self.position = position;
+        self
+    }
+
+    pub fn set_direction(&mut self, direction: Vec3) -> &mut Self {
+        // kept as is when already normalized, so that saved scenes load back exactly
+        self.direction = if direction.is_normalized() {
+            direction
+        } else {
+            direction.try_normalize().unwrap_or(self.direction)
+        };
+        self
+    }
+
+    pub fn set_shadow(&mut self, shadow: Option<Shadow>) -> &mut Self {
+        self.shadow = shadow;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    pub fn shadow(&self) -> Option<&Shadow> {
+        self.shadow.as_ref()
+    }
+
+    /// Projection of the shadowed area seen from the light, `None` without shadows
+    pub fn shadow_view_proj(&self) -> Option<Mat4> {
+        let shadow = self.shadow?;
+        let up = if self.direction.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+        let eye = self.position - self.direction * shadow.depth * 0.5;
+        let view = Mat4::look_at_rh(eye, self.position, up);
+        let extent = shadow.extent;
+        let projection = Mat4::orthographic_rh_gl(-extent, extent, -extent, extent, 0.0, shadow.depth);
+        Some(projection * view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec4Swizzles;
+
+    use super::*;
+
+    #[test]
+    fn shadow_volume_surrounds_the_position() {
+        let light = Light::new()
+            .with_position(vec3(2.0, 0.0, 1.0))
+            .with_direction(vec3(0.0, -1.0, 0.0))
+            .with_shadow(Shadow::new().with_area(5.0, 20.0));
+        let view_proj = light.shadow_view_proj().unwrap();
+
+        let center = view_proj * vec3(2.0, 0.0, 1.0).extend(1.0);
+        assert!(center.xyz().abs_diff_eq(Vec3::ZERO, 1.0e-5));
+        // closer to the light means a smaller depth
+        let above = view_proj * vec3(2.0, 5.0, 1.0).extend(1.0);
+        assert!(above.z < center.z);
+        let corner = view_proj * vec3(7.0, 0.0, 6.0).extend(1.0);
+        assert!((corner.x.abs() - 1.0).abs() < 1.0e-5 && (corner.y.abs() - 1.0).abs() < 1.0e-5);
+
+        assert!(Light::new().shadow_view_proj().is_none());
+    }
+}
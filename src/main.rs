@@ -9,9 +9,28 @@ mod color;
 mod light;
 mod gui;
 mod inputs;
+mod transform;
+mod scene;
+mod scene_file;
+mod animation;
+mod skeleton;
+mod camera_controller;
+mod picking;
+mod collision;
+mod physics;
+mod character;
+mod particles;
+mod texture;
+mod billboard;
+mod sky;
+mod material;
+mod debug_draw;
+mod shapes_2d;
+mod resolution;
+mod capture;
 
-use crate::console::Console;
+use crate::console::{Config, Console};
 
 fn main() {
-    Console::boot();
+    Console::boot(Config::default());
 }
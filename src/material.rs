@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, light::Shading, renderer::RenderMode, texture::Texture};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Writes the depth, the vertex alpha still blends with what is already drawn
+    #[default]
+    Opaque,
+    /// Sorted back to front with the other transparent geometry
+    Alpha,
+    /// Adds its color to what is behind, for lights and fire
+    Additive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CullMode {
+    /// Double sided
+    #[default]
+    None,
+    /// Hides the faces seen from behind, counter-clockwise faces are the front
+    Back,
+    Front,
+}
+
+/// How an object is drawn, objects sharing a material are batched together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    /// Multiplied with the vertex colors
+    pub color: Color,
+    pub texture: Option<Texture>,
+    pub shading: Shading,
+    /// Added to the lit color, glows in the dark
+    pub emissive: Color,
+    pub blend: BlendMode,
+    pub cull: CullMode,
+    /// Draws the edges of the triangles only
+    pub wireframe: bool,
+    /// Debug view of this material, overridden by the render mode of the frame
+    pub render_mode: RenderMode,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: Color::white(),
+            texture: None,
+            shading: Shading::Flat,
+            emissive: Color::new(0.0, 0.0, 0.0, 0.0),
+            blend: BlendMode::Opaque,
+            cull: CullMode::None,
+            wireframe: false,
+            render_mode: RenderMode::Shaded,
+        }
+    }
+}
+
+impl Material {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Vertex colors and texture only, for sprites and effects
+    pub fn unlit() -> Self {
+        Self::new().with_shading(Shading::Unlit)
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_shading(mut self, shading: Shading) -> Self {
+        self.shading = shading;
+        self
+    }
+
+    /// Blinn-Phong shading with highlights of the given intensity and sharpness
+    pub fn with_specular(self, specular: f32, shininess: f32) -> Self {
+        self.with_shading(Shading::BlinnPhong { specular, shininess })
+    }
+
+    pub fn with_emissive(mut self, emissive: Color) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_cull(mut self, cull: CullMode) -> Self {
+        self.cull = cull;
+        self
+    }
+
+    pub fn with_wireframe(mut self, wireframe: bool) -> Self {
+        self.wireframe = wireframe;
+        self
+    }
+
+    pub fn with_render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.render_mode = render_mode;
+        self
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend != BlendMode::Opaque
+    }
+
+    /// Same render states, the color doesn't matter as `Graphics::draw_object` bakes it into the vertices
+    pub fn batches_with(&self, other: &Material) -> bool {
+        self.texture == other.texture
+            && self.shading == other.shading
+            && self.emissive == other.emissive
+            && self.blend == other.blend
+            && self.cull == other.cull
+            && self.wireframe == other.wireframe
+            && self.render_mode == other.render_mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builders() {
+        let material = Material::new().with_specular(0.5, 8.0).with_blend(BlendMode::Additive);
+        assert_eq!(material.shading, Shading::BlinnPhong { specular: 0.5, shininess: 8.0 });
+        assert!(material.is_transparent());
+        assert!(!Material::unlit().is_transparent());
+        assert_ne!(Material::unlit(), Material::new());
+        assert!(Material::new().with_color(Color::red()).batches_with(&Material::new()));
+        assert!(!Material::unlit().batches_with(&Material::new()));
+    }
+}
@@ -1,21 +1,41 @@
 use glam::{Mat4, Vec3};
 
-use crate::{color::Color, graphics::Vertex, shapes::{Cube, Plane}};
+use crate::{color::Color, graphics::Vertex, light::Shading, material::Material, shapes::{Cube, Plane}};
 
-pub struct Object {
-    vertices: Vec<Vertex>,
-    indices: Vec<i32>,
-    transform: Mat4,
+/// Per-vertex offsets blended on top of the base mesh
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    pub name: String,
+    pub position_deltas: Vec<Vec3>,
+    /// Can be left empty when the target does not change the normals
+    pub normal_deltas: Vec<Vec3>,
 }
 
-impl Default for Object {
-    fn default() -> Self {
-        Self { 
-            vertices: Default::default(), 
-            indices: Default::default(), 
-            transform: Default::default() 
+impl MorphTarget {
+    pub fn new(name: &str, position_deltas: Vec<Vec3>) -> Self {
+        Self {
+            name: name.to_string(),
+            position_deltas,
+            normal_deltas: Vec::new(),
         }
     }
+
+    pub fn with_normal_deltas(mut self, normal_deltas: Vec<Vec3>) -> Self {
+        self.normal_deltas = normal_deltas;
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Object {
+    vertices: Vec<Vertex>,
+    indices: Vec<i32>,
+    transform: Mat4,
+    mesh_name: Option<String>,
+    material: Material,
+    base_vertices: Vec<Vertex>,
+    morph_targets: Vec<MorphTarget>,
+    morph_weights: Vec<f32>,
 }
 
 impl Object {
@@ -29,20 +49,49 @@ impl Object {
 
     pub fn new_cube(color: Color) -> Self {
         Self {
-            vertices: Cube::vertices(color),
+            vertices: Cube::vertices(Color::white()),
             indices: Cube::indices(),
             transform: Mat4::IDENTITY,
+            mesh_name: Some(Cube::NAME.to_string()),
+            material: Material::new().with_color(color),
+            ..Default::default()
         }
     }
 
     pub fn new_plane(color: Color) -> Self {
         Self {
-            vertices: Plane::vertices(color),
+            vertices: Plane::vertices(Color::white()),
             indices: Plane::indices(),
             transform: Mat4::IDENTITY,
+            mesh_name: Some(Plane::NAME.to_string()),
+            material: Material::new().with_color(color),
+            ..Default::default()
         }
     }
 
+    /// Names the mesh so that the object can be saved in a scene file
+    /// and instantiated back from a `MeshLibrary`
+    pub fn with_mesh_name(mut self, name: &str) -> Self {
+        self.mesh_name = Some(name.to_string());
+        self
+    }
+
+    /// Tints the vertex colors of the mesh when it is drawn
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.material.color = color;
+        self
+    }
+
+    pub fn with_shading(mut self, shading: Shading) -> Self {
+        self.material.shading = shading;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
     pub fn with_transform(mut self, transform: &Mat4) -> Self {
         self.transform = *transform;
         self
@@ -73,6 +122,95 @@ impl Object {
         self
     }
 
+    pub fn set_transform(&mut self, transform: &Mat4) -> &mut Self {
+        self.transform = *transform;
+        self
+    }
+
+    pub fn with_morph_target(mut self, target: MorphTarget) -> Self {
+        self.add_morph_target(target);
+        self
+    }
+
+    /// Adds a blend shape with a zero weight and returns its index
+    pub fn add_morph_target(&mut self, target: MorphTarget) -> usize {
+        assert_eq!(target.position_deltas.len(), self.vertices.len(), "one position delta per vertex is required");
+        assert!(
+            target.normal_deltas.is_empty() || target.normal_deltas.len() == self.vertices.len(),
+            "one normal delta per vertex is required"
+        );
+
+        if self.morph_targets.is_empty() {
+            self.base_vertices = self.vertices.clone();
+        }
+        self.morph_targets.push(target);
+        self.morph_weights.push(0.0);
+        self.morph_targets.len() - 1
+    }
+
+    pub fn morph_targets(&self) -> &[MorphTarget] {
+        &self.morph_targets
+    }
+
+    pub fn morph_target_index(&self, name: &str) -> Option<usize> {
+        self.morph_targets.iter().position(|target| target.name == name)
+    }
+
+    pub fn morph_weights(&self) -> &[f32] {
+        &self.morph_weights
+    }
+
+    pub fn set_morph_weight(&mut self, index: usize, weight: f32) -> &mut Self {
+        self.morph_weights[index] = weight;
+        self.blend_morph_targets();
+        self
+    }
+
+    pub fn set_morph_weights(&mut self, weights: &[f32]) -> &mut Self {
+        for (w, weight) in self.morph_weights.iter_mut().zip(weights.iter()) {
+            *w = *weight;
+        }
+        self.blend_morph_targets();
+        self
+    }
+
+    fn blend_morph_targets(&mut self) {
+        let vertices = self.vertices
+            .iter_mut()
+            .zip(self.base_vertices.iter())
+            .enumerate();
+        for (i, (vertex, base)) in vertices {
+            let mut position = Vec3::from(base.position);
+            let mut normal = Vec3::from(base.normal);
+            for (target, weight) in self.morph_targets.iter().zip(self.morph_weights.iter()) {
+                if *weight == 0.0 {
+                    continue;
+                }
+                position += target.position_deltas[i] * *weight;
+                if let Some(delta) = target.normal_deltas.get(i) {
+                    normal += *delta * *weight;
+                }
+            }
+            vertex.position = position.to_array();
+            vertex.normal = normal.normalize_or_zero().to_array();
+        }
+    }
+
+    pub fn set_color(&mut self, color: Color) -> &mut Self {
+        self.material.color = color;
+        self
+    }
+
+    pub fn set_shading(&mut self, shading: Shading) -> &mut Self {
+        self.material.shading = shading;
+        self
+    }
+
+    pub fn set_material(&mut self, material: Material) -> &mut Self {
+        self.material = material;
+        self
+    }
+
     pub fn translate(&mut self, translation: Vec3) -> &mut Self {
         self.transform *= Mat4::from_translation(translation);
         self
@@ -106,8 +244,55 @@ impl Object {
         &self.vertices
     }
 
+    pub fn vertices_mut(&mut self) -> &mut Vec<Vertex> {
+        &mut self.vertices
+    }
+
     pub fn indices(&self) -> &Vec<i32> {
         &self.indices
     }
+
+    pub fn mesh_name(&self) -> Option<&str> {
+        self.mesh_name.as_deref()
+    }
+
+    pub fn color(&self) -> Color {
+        self.material.color
+    }
+
+    pub fn shading(&self) -> Shading {
+        self.material.shading
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    #[test]
+    fn morph_targets_are_blended_from_the_base_mesh() {
+        let mut plane = Object::new_plane(Color::white())
+            .with_morph_target(MorphTarget::new("bump", vec![vec3(0.0, 1.0, 0.0); 4]))
+            .with_morph_target(MorphTarget::new("shift", vec![vec3(2.0, 0.0, 0.0); 4]));
+
+        plane.set_morph_weight(0, 0.5);
+        plane.set_morph_weight(0, 0.5);
+        assert_eq!(plane.vertices()[0].position, [-0.5, 0.5, -0.5]);
+
+        plane.set_morph_weights(&[1.0, 0.25]);
+        assert_eq!(plane.vertices()[3].position, [1.0, 1.0, 0.5]);
+
+        plane.set_morph_weights(&[0.0, 0.0]);
+        assert_eq!(plane.vertices()[3].position, [0.5, 0.0, 0.5]);
+    }
+}
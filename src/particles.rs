@@ -0,0 +1,351 @@
+use glam::{vec3, Vec3};
+
+use crate::{animation::{Easing, Track}, color::Color, time::TimeStep};
+
+/// Small xorshift generator, emitters with the same seed spawn the same particles
+#[derive(Debug, Clone)]
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    fn range_vec3(&mut self, min: Vec3, max: Vec3) -> Vec3 {
+        vec3(self.range(min.x, max.x), self.range(min.y, max.y), self.range(min.z, max.z))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Age between 0 at spawn and 1 at death
+    pub fn progress(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Burst {
+    /// Emitter time of the burst in seconds
+    pub time: f32,
+    pub count: usize,
+}
+
+/// Spawns and simulates particles, drawn with `Graphics::draw_particles`.
+/// Positions are in world units in 3D and in pixels in 2D.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    position: Vec3,
+    rate: f32,
+    bursts: Vec<Burst>,
+    lifetime: (f32, f32),
+    velocity: (Vec3, Vec3),
+    spawn_area: (Vec3, Vec3),
+    gravity: Vec3,
+    drag: f32,
+    color: Track<Color>,
+    size: Track<f32>,
+    max_particles: usize,
+    emitting: bool,
+    particles: Vec<Particle>,
+    time: f32,
+    spawn_accumulator: f32,
+    rng: Rng,
+}
+
+impl ParticleEmitter {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            rate: 10.0,
+            bursts: Vec::new(),
+            lifetime: (1.0, 1.0),
+            velocity: (Vec3::ZERO, Vec3::ZERO),
+            spawn_area: (Vec3::ZERO, Vec3::ZERO),
+            gravity: Vec3::ZERO,
+            drag: 0.0,
+            color: Track::new().with_keyframe(0.0, Color::white(), Easing::Linear),
+            size: Track::new().with_keyframe(0.0, 0.1, Easing::Linear),
+            max_particles: 1000,
+            emitting: true,
+            particles: Vec::new(),
+            time: 0.0,
+            spawn_accumulator: 0.0,
+            rng: Rng(0x9e37_79b9),
+        }
+    }
+
+    /// Fireball of particles thrown in every direction, then fading out
+    pub fn explosion(position: Vec3, color: Color) -> Self {
+        Self::new(position)
+            .with_rate(0.0)
+            .with_burst(0.0, 200)
+            .with_lifetime(0.4, 0.9)
+            .with_velocity(Vec3::splat(-4.0), Vec3::splat(4.0))
+            .with_drag(3.0)
+            .with_color_over_lifetime(
+                Track::new()
+                    .with_keyframe(0.0, Color::white(), Easing::Linear)
+                    .with_keyframe(0.2, color, Easing::Linear)
+                    .with_keyframe(1.0, Color::new(color.r, color.g, color.b, 0.0), Easing::Linear),
+            )
+            .with_size_over_lifetime(Track::new().with_keyframe(0.0, 0.3, Easing::QuadOut).with_keyframe(1.0, 0.05, Easing::Linear))
+    }
+
+    /// Slow grey puffs rising and growing
+    pub fn smoke(position: Vec3) -> Self {
+        Self::new(position)
+            .with_rate(15.0)
+            .with_lifetime(2.0, 3.0)
+            .with_spawn_area(vec3(-0.2, 0.0, -0.2), vec3(0.2, 0.0, 0.2))
+            .with_velocity(vec3(-0.2, 0.5, -0.2), vec3(0.2, 1.0, 0.2))
+            .with_color_over_lifetime(
+                Track::new()
+                    .with_keyframe(0.0, Color::new(0.5, 0.5, 0.5, 0.6), Easing::Linear)
+                    .with_keyframe(1.0, Color::new(0.3, 0.3, 0.3, 0.0), Easing::Linear),
+            )
+            .with_size_over_lifetime(Track::new().with_keyframe(0.0, 0.2, Easing::Linear).with_keyframe(1.0, 0.8, Easing::Linear))
+    }
+
+    /// Fast bright sparks falling with gravity
+    pub fn sparks(position: Vec3, color: Color) -> Self {
+        Self::new(position)
+            .with_rate(60.0)
+            .with_lifetime(0.3, 0.7)
+            .with_velocity(vec3(-2.0, 2.0, -2.0), vec3(2.0, 5.0, 2.0))
+            .with_gravity(vec3(0.0, -9.81, 0.0))
+            .with_color_over_lifetime(
+                Track::new()
+                    .with_keyframe(0.0, Color::white(), Easing::Linear)
+                    .with_keyframe(0.5, color, Easing::Linear)
+                    .with_keyframe(1.0, Color::new(color.r, color.g, color.b, 0.0), Easing::Linear),
+            )
+            .with_size(0.04)
+    }
+
+    /// Particles spawned per second, 0 to only use bursts
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    pub fn with_burst(mut self, time: f32, count: usize) -> Self {
+        self.bursts.push(Burst { time, count });
+        self
+    }
+
+    pub fn with_lifetime(mut self, min: f32, max: f32) -> Self {
+        self.lifetime = (min, max);
+        self
+    }
+
+    /// Initial velocities are picked between `min` and `max` on each axis
+    pub fn with_velocity(mut self, min: Vec3, max: Vec3) -> Self {
+        self.velocity = (min, max);
+        self
+    }
+
+    /// Offsets from the emitter position where particles are spawned
+    pub fn with_spawn_area(mut self, min: Vec3, max: Vec3) -> Self {
+        self.spawn_area = (min, max);
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: Vec3) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Track::new().with_keyframe(0.0, color, Easing::Linear);
+        self
+    }
+
+    /// Color keyed by the particle progress, from 0 at spawn to 1 at death
+    pub fn with_color_over_lifetime(mut self, color: Track<Color>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = Track::new().with_keyframe(0.0, size, Easing::Linear);
+        self
+    }
+
+    /// Size keyed by the particle progress, from 0 at spawn to 1 at death
+    pub fn with_size_over_lifetime(mut self, size: Track<f32>) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = Rng(seed.max(1));
+        self
+    }
+
+    pub fn set_position(&mut self, position: Vec3) -> &mut Self {
+        self.position = position;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    /// Resumes the continuous emission and the scheduled bursts
+    pub fn start(&mut self) -> &mut Self {
+        self.emitting = true;
+        self
+    }
+
+    /// Stops spawning, the living particles keep going until they die
+    pub fn stop(&mut self) -> &mut Self {
+        self.emitting = false;
+        self
+    }
+
+    /// Restarts the emitter time and removes every particle
+    pub fn restart(&mut self) -> &mut Self {
+        self.particles.clear();
+        self.time = 0.0;
+        self.spawn_accumulator = 0.0;
+        self.emitting = true;
+        self
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// No particle is alive and none will be spawned anymore
+    pub fn is_finished(&self) -> bool {
+        let pending_bursts = self.bursts.iter().any(|burst| burst.time >= self.time);
+        self.particles.is_empty() && (!self.emitting || (self.rate <= 0.0 && !pending_bursts))
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn color(&self, particle: &Particle) -> Color {
+        self.color.sample(particle.progress()).unwrap_or(Color::white())
+    }
+
+    pub fn size(&self, particle: &Particle) -> f32 {
+        self.size.sample(particle.progress()).unwrap_or(0.0)
+    }
+
+    /// Spawns `count` particles right away
+    pub fn burst(&mut self, count: usize) -> &mut Self {
+        for _ in 0..count {
+            if self.particles.len() >= self.max_particles {
+                break;
+            }
+            let particle = Particle {
+                position: self.position + self.rng.range_vec3(self.spawn_area.0, self.spawn_area.1),
+                velocity: self.rng.range_vec3(self.velocity.0, self.velocity.1),
+                age: 0.0,
+                lifetime: self.rng.range(self.lifetime.0, self.lifetime.1).max(f32::EPSILON),
+            };
+            self.particles.push(particle);
+        }
+        self
+    }
+
+    pub fn advance(&mut self, dt: f32) -> &mut Self {
+        let damping = 1.0 / (1.0 + self.drag * dt);
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity = (particle.velocity + self.gravity * dt) * damping;
+            particle.position += particle.velocity * dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if self.emitting {
+            let previous = self.time;
+            let bursts: usize = self
+                .bursts
+                .iter()
+                .filter(|burst| burst.time >= previous && burst.time < previous + dt)
+                .map(|burst| burst.count)
+                .sum();
+            self.burst(bursts);
+
+            self.spawn_accumulator += self.rate * dt;
+            let count = self.spawn_accumulator as usize;
+            self.spawn_accumulator -= count as f32;
+            self.burst(count);
+        }
+
+        self.time += dt;
+        self
+    }
+
+    pub fn update(&mut self, time_step: &TimeStep) -> &mut Self {
+        self.advance(time_step.delta_time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_bursts_and_lifetime() {
+        let mut emitter = ParticleEmitter::new(Vec3::ZERO).with_rate(10.0).with_burst(0.0, 5).with_lifetime(1.0, 1.0);
+        emitter.advance(0.5);
+        assert_eq!(emitter.particles().len(), 10);
+
+        emitter.advance(0.6);
+        assert_eq!(emitter.particles().len(), 16);
+        assert!(emitter.particles().iter().all(|p| p.age < 1.0));
+
+        emitter.stop().advance(1.0);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn particles_follow_gravity_and_fade() {
+        let mut emitter = ParticleEmitter::new(vec3(0.0, 1.0, 0.0))
+            .with_rate(0.0)
+            .with_burst(0.0, 1)
+            .with_lifetime(2.0, 2.0)
+            .with_gravity(vec3(0.0, -10.0, 0.0))
+            .with_color_over_lifetime(
+                Track::new()
+                    .with_keyframe(0.0, Color::white(), Easing::Linear)
+                    .with_keyframe(1.0, Color::new(1.0, 1.0, 1.0, 0.0), Easing::Linear),
+            );
+        for _ in 0..11 {
+            emitter.advance(0.1);
+        }
+
+        let particle = emitter.particles()[0];
+        assert!(particle.position.y < 1.0 - 5.0 * 0.9);
+        assert!((emitter.color(&particle).a - 0.5).abs() < 1.0e-4);
+    }
+}
@@ -0,0 +1,636 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{collision::{Collider, ColliderId, CollisionWorld}, object::Object, time::FixedStep};
+
+const SOLVER_ITERATIONS: usize = 8;
+const POSITION_CORRECTION: f32 = 0.8;
+const PENETRATION_SLOP: f32 = 0.005;
+/// Below this approach speed contacts don't bounce, so resting bodies settle
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+const SLEEP_SPEED: f32 = 0.05;
+const SLEEP_TIME: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyType {
+    #[default]
+    Dynamic,
+    /// Never moves
+    Static,
+    /// Moved by its velocity only, pushes dynamic bodies without being pushed back
+    Kinematic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RigidBody {
+    body_type: BodyType,
+    collider: Collider,
+    position: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+    inverse_mass: f32,
+    friction: f32,
+    restitution: f32,
+    gravity_scale: f32,
+    linear_damping: f32,
+    angular_damping: f32,
+    sleeping: bool,
+    sleep_timer: f32,
+}
+
+impl RigidBody {
+    pub fn new(body_type: BodyType, collider: Collider) -> Self {
+        Self {
+            body_type,
+            collider,
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            inverse_mass: if body_type == BodyType::Dynamic { 1.0 } else { 0.0 },
+            friction: 0.5,
+            restitution: 0.0,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.05,
+            sleeping: false,
+            sleep_timer: 0.0,
+        }
+    }
+
+    pub fn dynamic(collider: Collider, mass: f32) -> Self {
+        Self::new(BodyType::Dynamic, collider).with_mass(mass)
+    }
+
+    pub fn fixed(collider: Collider) -> Self {
+        Self::new(BodyType::Static, collider)
+    }
+
+    pub fn kinematic(collider: Collider) -> Self {
+        Self::new(BodyType::Kinematic, collider)
+    }
+
+    /// Ignored for static and kinematic bodies
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        if self.body_type == BodyType::Dynamic && mass > 0.0 {
+            self.inverse_mass = 1.0 / mass;
+        }
+        self
+    }
+
+    pub fn with_position(mut self, position: Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Takes position, rotation and scale from a transform such as `Object::transform`
+    pub fn with_transform(mut self, transform: &Mat4) -> Self {
+        (self.scale, self.rotation, self.position) = transform.to_scale_rotation_translation();
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_angular_velocity(mut self, angular_velocity: Vec3) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    pub fn with_damping(mut self, linear: f32, angular: f32) -> Self {
+        self.linear_damping = linear;
+        self.angular_damping = angular;
+        self
+    }
+
+    pub fn body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    pub fn collider(&self) -> &Collider {
+        &self.collider
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn angular_velocity(&self) -> Vec3 {
+        self.angular_velocity
+    }
+
+    pub fn mass(&self) -> f32 {
+        if self.inverse_mass > 0.0 { 1.0 / self.inverse_mass } else { f32::INFINITY }
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
+    fn wake_up(&mut self) {
+        self.sleeping = false;
+        self.sleep_timer = 0.0;
+    }
+
+    /// Inverse mass taking part in the contact resolution
+    fn solver_inverse_mass(&self) -> f32 {
+        if self.sleeping { 0.0 } else { self.inverse_mass }
+    }
+}
+
+/// Part of a body the contact resolution works with
+#[derive(Clone, Copy)]
+struct ContactBody {
+    velocity: Vec3,
+    inverse_mass: f32,
+    friction: f32,
+    restitution: f32,
+}
+
+impl ContactBody {
+    fn new(body: &RigidBody) -> Self {
+        Self {
+            velocity: body.velocity,
+            inverse_mass: body.solver_inverse_mass(),
+            friction: body.friction,
+            restitution: body.restitution,
+        }
+    }
+}
+
+/// Slots are reused after a removal, the generation tells a stale id from the body now in its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyId {
+    index: usize,
+    generation: u32,
+}
+
+impl BodyId {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Rigid bodies simulated with fixed steps, collisions are resolved with impulses.
+/// Bodies don't pick up spin from contacts, only their velocity is affected.
+pub struct PhysicsWorld {
+    bodies: Vec<Option<(RigidBody, ColliderId)>>,
+    /// Bumped each time the body of a slot is removed
+    generations: Vec<u32>,
+    collisions: CollisionWorld,
+    gravity: Vec3,
+    fixed_step: FixedStep,
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            bodies: Vec::new(),
+            generations: Vec::new(),
+            collisions: CollisionWorld::new(),
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            fixed_step: FixedStep::default(),
+        }
+    }
+
+    pub fn with_gravity(mut self, gravity: Vec3) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_fixed_step(mut self, fixed_step: FixedStep) -> Self {
+        self.fixed_step = fixed_step;
+        self
+    }
+
+    pub fn gravity(&self) -> Vec3 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vec3) -> &mut Self {
+        self.gravity = gravity;
+        self.wake_all();
+        self
+    }
+
+    pub fn add(&mut self, body: RigidBody) -> BodyId {
+        let collider = self.collisions.add(body.collider.clone(), &body.transform());
+        let entry = Some((body, collider));
+        let index = match self.bodies.iter().position(|slot| slot.is_none()) {
+            Some(index) => {
+                self.bodies[index] = entry;
+                index
+            }
+            None => {
+                self.bodies.push(entry);
+                self.generations.push(0);
+                self.bodies.len() - 1
+            }
+        };
+        self.id(index)
+    }
+
+    pub fn remove(&mut self, id: BodyId) -> Option<RigidBody> {
+        if !self.is_current(id) {
+            return None;
+        }
+        let (body, collider) = self.bodies[id.index].take()?;
+        self.generations[id.index] += 1;
+        self.collisions.remove(collider);
+        self.wake_all();
+        Some(body)
+    }
+
+    pub fn contains(&self, id: BodyId) -> bool {
+        self.body(id).is_some()
+    }
+
+    pub fn body(&self, id: BodyId) -> Option<&RigidBody> {
+        if !self.is_current(id) {
+            return None;
+        }
+        self.bodies[id.index].as_ref().map(|(body, _)| body)
+    }
+
+    fn body_mut(&mut self, id: BodyId) -> Option<&mut RigidBody> {
+        if !self.is_current(id) {
+            return None;
+        }
+        self.bodies[id.index].as_mut().map(|(body, _)| body)
+    }
+
+    fn is_current(&self, id: BodyId) -> bool {
+        self.generations.get(id.index) == Some(&id.generation)
+    }
+
+    fn id(&self, index: usize) -> BodyId {
+        BodyId { index, generation: self.generations[index] }
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyId, &RigidBody)> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|(body, _)| (self.id(i), body)))
+    }
+
+    /// Collisions found during the last step, to react to contacts and triggers
+    pub fn collisions(&self) -> &CollisionWorld {
+        &self.collisions
+    }
+
+    /// Body owning a collider of `collisions`
+    pub fn body_of(&self, collider: ColliderId) -> Option<BodyId> {
+        self.bodies
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|(_, id)| *id == collider))
+            .map(|i| self.id(i))
+    }
+
+    pub fn set_position(&mut self, id: BodyId, position: Vec3) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.position = position;
+            body.wake_up();
+        }
+        self
+    }
+
+    pub fn set_rotation(&mut self, id: BodyId, rotation: Quat) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.rotation = rotation;
+            body.wake_up();
+        }
+        self
+    }
+
+    pub fn set_velocity(&mut self, id: BodyId, velocity: Vec3) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.velocity = velocity;
+            body.wake_up();
+        }
+        self
+    }
+
+    pub fn set_angular_velocity(&mut self, id: BodyId, angular_velocity: Vec3) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.angular_velocity = angular_velocity;
+            body.wake_up();
+        }
+        self
+    }
+
+    pub fn apply_impulse(&mut self, id: BodyId, impulse: Vec3) -> &mut Self {
+        if let Some(body) = self.body_mut(id) {
+            body.velocity += impulse * body.inverse_mass;
+            body.wake_up();
+        }
+        self
+    }
+
+    fn wake_all(&mut self) {
+        for (body, _) in self.bodies.iter_mut().flatten() {
+            body.wake_up();
+        }
+    }
+
+    /// Runs as many fixed steps as fit in `dt`, to be called once per frame
+    pub fn update(&mut self, dt: f32) {
+        for _ in 0..self.fixed_step.advance(dt) {
+            self.step(self.fixed_step.step());
+        }
+    }
+
+    /// Advances the simulation by exactly `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        for (body, _) in self.bodies.iter_mut().flatten() {
+            if body.body_type == BodyType::Dynamic && !body.sleeping {
+                body.velocity += self.gravity * body.gravity_scale * dt;
+                body.velocity /= 1.0 + body.linear_damping * dt;
+                body.angular_velocity /= 1.0 + body.angular_damping * dt;
+            }
+        }
+
+        for (body, collider) in self.bodies.iter().flatten() {
+            self.collisions.set_transform(*collider, &body.transform());
+        }
+        self.collisions.update();
+
+        let mut owners = Vec::new();
+        for (i, (_, collider)) in self.bodies.iter().enumerate().filter_map(|(i, slot)| Some((i, slot.as_ref()?))) {
+            if owners.len() <= collider.index() {
+                owners.resize(collider.index() + 1, None);
+            }
+            owners[collider.index()] = Some(self.id(i));
+        }
+        let contacts: Vec<_> = self
+            .collisions
+            .contacts()
+            .iter()
+            .filter_map(|pair| Some((owners[pair.a.index()]?, owners[pair.b.index()]?, pair.contact)))
+            .collect();
+
+        // a moving body wakes up the sleeping ones it touches
+        for &(a, b, _) in &contacts {
+            let awake = |id: BodyId, world: &Self| {
+                world.body(id).is_some_and(|body| {
+                    body.body_type != BodyType::Static && !body.sleeping && body.velocity.length_squared() > SLEEP_SPEED * SLEEP_SPEED
+                })
+            };
+            if awake(a, self) {
+                if let Some(body) = self.body_mut(b) {
+                    body.wake_up();
+                }
+            }
+            if awake(b, self) {
+                if let Some(body) = self.body_mut(a) {
+                    body.wake_up();
+                }
+            }
+        }
+
+        for _ in 0..SOLVER_ITERATIONS {
+            for &(a, b, contact) in &contacts {
+                self.resolve_velocity(a, b, contact.normal);
+            }
+        }
+
+        for (body, _) in self.bodies.iter_mut().flatten() {
+            if body.body_type == BodyType::Static || body.sleeping {
+                continue;
+            }
+            body.position += body.velocity * dt;
+            let spin = body.angular_velocity * dt;
+            if spin != Vec3::ZERO {
+                body.rotation = (Quat::from_scaled_axis(spin) * body.rotation).normalize();
+            }
+        }
+
+        for &(a, b, contact) in &contacts {
+            self.correct_position(a, b, contact.normal, contact.depth);
+        }
+
+        for (body, collider) in self.bodies.iter_mut().flatten() {
+            if body.body_type != BodyType::Dynamic || body.sleeping {
+                continue;
+            }
+            let speed = body.velocity.length_squared().max(body.angular_velocity.length_squared());
+            if speed < SLEEP_SPEED * SLEEP_SPEED {
+                body.sleep_timer += dt;
+                if body.sleep_timer >= SLEEP_TIME {
+                    body.sleeping = true;
+                    body.velocity = Vec3::ZERO;
+                    body.angular_velocity = Vec3::ZERO;
+                }
+            } else {
+                body.sleep_timer = 0.0;
+            }
+            self.collisions.set_transform(*collider, &body.transform());
+        }
+    }
+
+    fn pair(&self, a: BodyId, b: BodyId) -> Option<(ContactBody, ContactBody)> {
+        Some((ContactBody::new(self.body(a)?), ContactBody::new(self.body(b)?)))
+    }
+
+    fn resolve_velocity(&mut self, a: BodyId, b: BodyId, normal: Vec3) {
+        let Some((body_a, body_b)) = self.pair(a, b) else {
+            return;
+        };
+        let inverse_mass_a = body_a.inverse_mass;
+        let inverse_mass_b = body_b.inverse_mass;
+        let total_inverse_mass = inverse_mass_a + inverse_mass_b;
+        if total_inverse_mass <= 0.0 {
+            return;
+        }
+
+        let relative = body_b.velocity - body_a.velocity;
+        let normal_speed = relative.dot(normal);
+        if normal_speed >= 0.0 {
+            return;
+        }
+
+        let restitution = if -normal_speed > RESTITUTION_THRESHOLD {
+            body_a.restitution.max(body_b.restitution)
+        } else {
+            0.0
+        };
+        let normal_impulse = -(1.0 + restitution) * normal_speed / total_inverse_mass;
+        let mut impulse = normal * normal_impulse;
+
+        let tangent_velocity = relative - normal * normal_speed;
+        let tangent_speed = tangent_velocity.length();
+        if tangent_speed > f32::EPSILON {
+            let friction = (body_a.friction * body_b.friction).sqrt();
+            let tangent_impulse = (tangent_speed / total_inverse_mass).min(friction * normal_impulse);
+            impulse -= tangent_velocity / tangent_speed * tangent_impulse;
+        }
+
+        if let Some(body) = self.body_mut(a) {
+            body.velocity -= impulse * inverse_mass_a;
+        }
+        if let Some(body) = self.body_mut(b) {
+            body.velocity += impulse * inverse_mass_b;
+        }
+    }
+
+    fn correct_position(&mut self, a: BodyId, b: BodyId, normal: Vec3, depth: f32) {
+        let Some((body_a, body_b)) = self.pair(a, b) else {
+            return;
+        };
+        let inverse_mass_a = body_a.inverse_mass;
+        let inverse_mass_b = body_b.inverse_mass;
+        let total_inverse_mass = inverse_mass_a + inverse_mass_b;
+        if total_inverse_mass <= 0.0 {
+            return;
+        }
+
+        let correction = normal * ((depth - PENETRATION_SLOP).max(0.0) / total_inverse_mass * POSITION_CORRECTION);
+        if let Some(body) = self.body_mut(a) {
+            body.position -= correction * inverse_mass_a;
+        }
+        if let Some(body) = self.body_mut(b) {
+            body.position += correction * inverse_mass_b;
+        }
+    }
+
+    /// Copies the body transform into the object it simulates
+    pub fn apply_to_object(&self, id: BodyId, object: &mut Object) {
+        if let Some(body) = self.body(id) {
+            object.set_transform(&body.transform());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use crate::time::FixedStep;
+
+    use super::*;
+
+    fn ground(world: &mut PhysicsWorld) -> BodyId {
+        world.add(RigidBody::fixed(Collider::aabb(vec3(10.0, 0.5, 10.0))).with_position(vec3(0.0, -0.5, 0.0)))
+    }
+
+    #[test]
+    fn falling_body_comes_to_rest_and_sleeps() {
+        let mut world = PhysicsWorld::new();
+        ground(&mut world);
+        let ball = world.add(RigidBody::dynamic(Collider::sphere(0.5), 1.0).with_position(vec3(0.0, 3.0, 0.0)));
+
+        for _ in 0..240 {
+            world.step(1.0 / 60.0);
+        }
+
+        let body = world.body(ball).unwrap();
+        assert!((body.position().y - 0.5).abs() < 0.02, "{}", body.position());
+        assert!(body.is_sleeping());
+
+        world.apply_impulse(ball, vec3(0.0, 5.0, 0.0));
+        assert!(!world.body(ball).unwrap().is_sleeping());
+    }
+
+    #[test]
+    fn restitution_bounces_and_friction_slows_down() {
+        let mut world = PhysicsWorld::new();
+        ground(&mut world);
+        let bouncy = world.add(
+            RigidBody::dynamic(Collider::sphere(0.5), 1.0)
+                .with_position(vec3(-5.0, 2.0, 0.0))
+                .with_restitution(0.8),
+        );
+        let slider = world.add(
+            RigidBody::dynamic(Collider::aabb(Vec3::splat(0.5)), 1.0)
+                .with_position(vec3(5.0, 0.5, 0.0))
+                .with_velocity(vec3(-3.0, 0.0, 0.0)),
+        );
+
+        let mut bounced = false;
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+            bounced |= world.body(bouncy).unwrap().velocity().y > 2.0;
+        }
+
+        assert!(bounced);
+        let slider = world.body(slider).unwrap();
+        assert!(slider.velocity().x.abs() < 0.01);
+        assert!(slider.position().x > 3.5);
+    }
+
+    #[test]
+    fn stale_ids_miss_reused_slots() {
+        let mut world = PhysicsWorld::new();
+        let ball = world.add(RigidBody::dynamic(Collider::sphere(0.5), 1.0));
+        world.remove(ball);
+        let box_body = world.add(RigidBody::dynamic(Collider::aabb(Vec3::ONE), 2.0));
+        assert_eq!(box_body.index(), ball.index());
+
+        assert!(!world.contains(ball));
+        assert!(world.remove(ball).is_none());
+        world.set_velocity(ball, vec3(1.0, 0.0, 0.0));
+        assert_eq!(world.body(box_body).unwrap().velocity(), Vec3::ZERO);
+        let collider = world.bodies[box_body.index()].as_ref().unwrap().1;
+        assert_eq!(world.body_of(collider), Some(box_body));
+    }
+
+    #[test]
+    fn fixed_steps_are_deterministic() {
+        let mut steps = FixedStep::new(0.01);
+        assert_eq!(steps.advance(0.025), 2);
+        assert_eq!(steps.advance(0.006), 1);
+        assert_eq!(steps.advance(1.0), 8);
+
+        let simulate = |frames: &[f32]| {
+            let mut world = PhysicsWorld::new().with_fixed_step(FixedStep::new(1.0 / 64.0));
+            ground(&mut world);
+            let id = world.add(RigidBody::dynamic(Collider::sphere(0.5), 1.0).with_position(vec3(0.0, 2.0, 0.0)));
+            for dt in frames {
+                world.update(*dt);
+            }
+            world.body(id).unwrap().position()
+        };
+        assert_eq!(simulate(&[1.0 / 32.0; 32]), simulate(&[1.0 / 16.0; 16]));
+    }
+}
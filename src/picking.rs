@@ -0,0 +1,250 @@
+use glam::{vec2, Mat4, Vec2, Vec3};
+
+use crate::{graphics::{Camera, Camera3d}, object::Object, renderer::screen_to_image, resolution::Resolution, scene::{NodeContent, NodeId, Scene}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Normalized direction
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Ray expressed in the local space of `transform`, the direction is not normalized
+    /// so that distances along it match the original ray
+    fn in_space(&self, transform: &Mat4) -> Ray {
+        let inverse = transform.inverse();
+        Ray {
+            origin: inverse.transform_point3(self.origin),
+            direction: inverse.transform_vector3(self.direction),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub distance: f32,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Index of the hit triangle, `None` when the bounds were hit
+    pub triangle: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PickMode {
+    /// Exact test against every triangle of the objects
+    #[default]
+    Triangles,
+    /// Cheap test against the axis aligned bounding box of the objects
+    Bounds,
+}
+
+impl Camera3d {
+    /// Ray starting on the near plane and going through a position in image pixels (top left origin)
+    pub fn image_ray(&self, image_pos: Vec2, resolution: &Resolution) -> Ray {
+        let viewport = self.viewport();
+        let image_size = resolution.as_vec2();
+        let origin = vec2(viewport.position.x, 1.0 - viewport.position.y - viewport.size.y) * image_size;
+        let size = viewport.size * image_size;
+        let local = (image_pos - origin) / size;
+        let ndc = vec2(local.x * 2.0 - 1.0, 1.0 - local.y * 2.0);
+
+        let inverse = self.view_proj(resolution).inverse();
+        let near = inverse.project_point3(ndc.extend(-1.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Ray::new(near, far - near)
+    }
+
+    /// Ray going from the camera through a position in window pixels, such as `Inputs::mouse_position`
+    pub fn screen_ray(&self, screen_pos: Vec2, screen_size: Vec2, resolution: &Resolution) -> Ray {
+        self.image_ray(screen_to_image(screen_pos, screen_size, resolution), resolution)
+    }
+}
+
+/// Two sided Möller–Trumbore intersection, returns the distance along the ray
+pub fn ray_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = ray.direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+    (t >= 0.0).then_some(t)
+}
+
+/// Slab test, returns the entry distance and the normal of the entered face
+pub fn ray_aabb(ray: &Ray, min: Vec3, max: Vec3) -> Option<(f32, Vec3)> {
+    let mut t_min = 0.0_f32;
+    let mut t_max = f32::INFINITY;
+    let mut normal = Vec3::ZERO;
+
+    for axis in 0..3 {
+        let origin = ray.origin[axis];
+        let direction = ray.direction[axis];
+        if direction.abs() < f32::EPSILON {
+            if origin < min[axis] || origin > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let inv = 1.0 / direction;
+        let mut t1 = (min[axis] - origin) * inv;
+        let mut t2 = (max[axis] - origin) * inv;
+        let mut face = Vec3::ZERO;
+        face[axis] = -1.0;
+        if t1 > t2 {
+            std::mem::swap(&mut t1, &mut t2);
+            face = -face;
+        }
+        if t1 > t_min {
+            t_min = t1;
+            normal = face;
+        }
+        t_max = t_max.min(t2);
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    Some((t_min, normal))
+}
+
+/// Local space bounding box of the object vertices
+pub fn local_bounds(object: &Object) -> Option<(Vec3, Vec3)> {
+    let mut vertices = object.vertices().iter().map(|v| Vec3::from(v.position));
+    let first = vertices.next()?;
+    Some(vertices.fold((first, first), |(min, max), p| (min.min(p), max.max(p))))
+}
+
+fn world_hit(ray: &Ray, transform: &Mat4, local_point: Vec3, local_normal: Vec3, triangle: Option<usize>) -> Hit {
+    let point = transform.transform_point3(local_point);
+    let normal = transform.inverse().transpose().transform_vector3(local_normal).normalize_or_zero();
+    Hit {
+        distance: (point - ray.origin).length(),
+        point,
+        normal,
+        triangle,
+    }
+}
+
+pub fn ray_object(ray: &Ray, object: &Object, mode: PickMode) -> Option<Hit> {
+    let local = ray.in_space(object.transform());
+    let (min, max) = local_bounds(object)?;
+    let (bounds_t, bounds_normal) = ray_aabb(&local, min, max)?;
+
+    if mode == PickMode::Bounds {
+        return Some(world_hit(ray, object.transform(), local.at(bounds_t), bounds_normal, None));
+    }
+
+    let vertices = object.vertices();
+    let mut closest: Option<(f32, usize, Vec3)> = None;
+    for (i, triangle) in object.indices().chunks_exact(3).enumerate() {
+        let a = Vec3::from(vertices[triangle[0] as usize].position);
+        let b = Vec3::from(vertices[triangle[1] as usize].position);
+        let c = Vec3::from(vertices[triangle[2] as usize].position);
+        if let Some(t) = ray_triangle(&local, a, b, c) {
+            if closest.is_none_or(|(closest_t, _, _)| t < closest_t) {
+                let mut normal = (b - a).cross(c - a).normalize_or_zero();
+                if normal.dot(local.direction) > 0.0 {
+                    normal = -normal;
+                }
+                closest = Some((t, i, normal));
+            }
+        }
+    }
+
+    closest.map(|(t, i, normal)| world_hit(ray, object.transform(), local.at(t), normal, Some(i)))
+}
+
+/// Closest object hit by the ray, with its index in `objects`
+pub fn pick<'a>(ray: &Ray, objects: impl IntoIterator<Item = &'a Object>, mode: PickMode) -> Option<(usize, Hit)> {
+    objects
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, object)| ray_object(ray, object, mode).map(|hit| (i, hit)))
+        .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+}
+
+impl Scene {
+    /// Closest object node hit by the ray. `Scene::update` must have been called
+    /// for the objects to be at their world position.
+    pub fn pick(&self, ray: &Ray, mode: PickMode) -> Option<(NodeId, Hit)> {
+        self.nodes()
+            .filter_map(|(id, node)| match node.content() {
+                NodeContent::Object(object) => ray_object(ray, object, mode).map(|hit| (id, hit)),
+                _ => None,
+            })
+            .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use crate::color::Color;
+
+    use super::*;
+
+    #[test]
+    fn ray_hits_triangle_from_both_sides() {
+        let (a, b, c) = (vec3(-1.0, -1.0, 0.0), vec3(1.0, -1.0, 0.0), vec3(0.0, 1.0, 0.0));
+        let front = Ray::new(vec3(0.0, 0.0, 2.0), vec3(0.0, 0.0, -1.0));
+        let back = Ray::new(vec3(0.0, 0.0, -3.0), vec3(0.0, 0.0, 1.0));
+        let miss = Ray::new(vec3(2.0, 0.0, 2.0), vec3(0.0, 0.0, -1.0));
+        assert_eq!(ray_triangle(&front, a, b, c), Some(2.0));
+        assert_eq!(ray_triangle(&back, a, b, c), Some(3.0));
+        assert_eq!(ray_triangle(&miss, a, b, c), None);
+    }
+
+    #[test]
+    fn picking_through_the_center_of_the_screen() {
+        let camera = Camera3d::new();
+        let resolution = Resolution::DEFAULT;
+        let ray = camera.image_ray(resolution.as_vec2() / 2.0, &resolution);
+        assert!(ray.direction.abs_diff_eq(vec3(0.0, 0.0, -1.0), 1.0e-4));
+
+        let near = Object::new_cube(Color::red()).with_translation(vec3(0.0, 0.0, 1.0));
+        let far = Object::new_cube(Color::red())
+            .with_transform(&Mat4::from_scale_rotation_translation(Vec3::splat(2.0), Default::default(), vec3(0.0, 0.0, -2.0)));
+        let aside = Object::new_cube(Color::red()).with_translation(vec3(3.0, 0.0, 1.0));
+
+        let (index, hit) = pick(&ray, [&far, &near, &aside], PickMode::Triangles).unwrap();
+        assert_eq!(index, 1);
+        assert!((hit.distance - (ray.origin.z - 1.5)).abs() < 1.0e-3);
+        assert!(hit.point.abs_diff_eq(vec3(0.0, 0.0, 1.5), 1.0e-3));
+        assert!(hit.normal.abs_diff_eq(vec3(0.0, 0.0, 1.0), 1.0e-3));
+
+        let (_, hit) = pick(&ray, [&far], PickMode::Bounds).unwrap();
+        assert!(hit.point.abs_diff_eq(vec3(0.0, 0.0, -1.0), 1.0e-3));
+        assert!(hit.normal.abs_diff_eq(vec3(0.0, 0.0, 1.0), 1.0e-3));
+    }
+}
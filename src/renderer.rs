@@ -1,53 +1,175 @@
+use std::{collections::HashMap, path::PathBuf, rc::Weak};
+
 use egui_miniquad::EguiMq;
-use glam::{uvec2, vec2, vec3, Mat4, UVec2};
+use glam::{vec2, Mat4, UVec2, Vec2, Vec3};
 use miniquad::*;
 
-use crate::{color::Color, graphics::{Rect2d, Vertex}, gui::Gui};
+use crate::{capture::{save_png, CaptureError, GifRecorder, Screenshot}, color::Color, debug_draw::TimedLine, graphics::{Fog, FogFalloff, Rect2d, Vertex}, gui::Gui, light::{Light, Shading, Shadow}, material::{BlendMode, CullMode, Material}, resolution::Resolution, sky::Sky, texture::{Cubemap, Texture}};
+
+/// Share of the window covered by the letterboxed image, on each axis
+pub fn display_scale(screen_size: Vec2, resolution: &Resolution) -> Vec2 {
+    let aspect = resolution.aspect();
+    if screen_size.x / screen_size.y > aspect {
+        vec2((screen_size.y / screen_size.x) * aspect, 1.0)
+    } else {
+        vec2(1.0, (screen_size.x / screen_size.y) / aspect)
+    }
+}
+
+/// Empty buffer for `count` elements of `T`, `None` when the current buffer of `size` bytes is large enough
+fn grown_buffer<'a, T>(size: usize, count: usize) -> Option<BufferSource<'a>> {
+    (size < count * std::mem::size_of::<T>()).then(|| BufferSource::empty::<T>(count.next_power_of_two()))
+}
+
+/// Converts a position in window pixels to a position in image pixels, both with a top left origin
+pub fn screen_to_image(screen_pos: Vec2, screen_size: Vec2, resolution: &Resolution) -> Vec2 {
+    let scale = display_scale(screen_size, resolution);
+    let ndc = vec2(
+        screen_pos.x / screen_size.x * 2.0 - 1.0,
+        1.0 - screen_pos.y / screen_size.y * 2.0,
+    ) / scale;
+    let image_size = resolution.as_vec2();
+    vec2(
+        (ndc.x + 1.0) / 2.0 * image_size.x,
+        (1.0 - ndc.y) / 2.0 * image_size.y,
+    )
+}
 
-pub const IMAGE_RES: UVec2 = uvec2(320, 200);
-pub const IMAGE_RATIO_XY: f32 = IMAGE_RES.x as f32 / IMAGE_RES.y as f32;
-pub const IMAGE_RATIO_YX: f32 = IMAGE_RES.y as f32 / IMAGE_RES.x as f32;
+/// Converts a position in image pixels to a position in window pixels, both with a top left origin
+pub fn image_to_screen(image_pos: Vec2, screen_size: Vec2, resolution: &Resolution) -> Vec2 {
+    let scale = display_scale(screen_size, resolution);
+    let image_size = resolution.as_vec2();
+    let ndc = vec2(
+        image_pos.x / image_size.x * 2.0 - 1.0,
+        1.0 - image_pos.y / image_size.y * 2.0,
+    ) * scale;
+    vec2(
+        (ndc.x + 1.0) / 2.0 * screen_size.x,
+        (1.0 - ndc.y) / 2.0 * screen_size.y,
+    )
+}
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Primitive {
     Lines,
     Triangles,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Mode {
     Mode2d,
     Mode3d,
 }
 
+/// Debug views of the 3D geometry, set for the whole frame with `Graphics::set_render_mode`
+/// or for a single object with its material
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderMode {
+    #[default]
+    Shaded,
+    /// Edges of the triangles drawn over the shaded objects
+    Wireframe,
+    /// World space normals as colors
+    Normals,
+    /// Distance to the camera, white is close
+    Depth,
+    /// Pixels brighten every time they are drawn, the background is black
+    Overdraw,
+    /// One color per draw call, to check the batching
+    DrawCalls,
+}
+
+impl RenderMode {
+    /// Cycles through the modes, bound to the debug key of the console
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Shaded => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Normals,
+            RenderMode::Normals => RenderMode::Depth,
+            RenderMode::Depth => RenderMode::Overdraw,
+            RenderMode::Overdraw => RenderMode::DrawCalls,
+            RenderMode::DrawCalls => RenderMode::Shaded,
+        }
+    }
+}
+
 pub struct RendererData {
     pub draw_calls: Vec<DrawCall>,
+    /// Transparent geometry waiting to be sorted, see `Graphics::flush_transparent`
+    pub transparent: Vec<TransparentDraw>,
     pub draw_calls_binding: Vec<Bindings>,
     pub draw_calls_count: usize,
     pub view_proj: Mat4,
+    pub camera_transform: Mat4,
     pub mode: Mode,
     pub viewport: Rect2d,
     pub background: Color,
+    /// Fog of the current camera, its color resolved
+    pub fog: Option<Fog>,
+    /// Main directional light of the frame
+    pub light: Light,
+    pub skies: Vec<SkyDraw>,
+    /// Lines of `debug_draw` waiting for the camera to change
+    pub debug_lines: Vec<Vertex>,
+    pub timed_lines: Vec<TimedLine>,
+    /// Pixels of `Graphics::pset`, kept from one frame to the next
+    pub canvas: Option<Texture>,
+    /// Kept from one frame to the next
+    pub render_mode: RenderMode,
+    /// Render target of the current camera
+    pub target: Option<Texture>,
+    /// Kept from one frame to the next, see `Graphics::set_resolution`
+    pub resolution: Resolution,
+    /// Saved at the end of the frame
+    pub screenshots: Vec<Screenshot>,
+    /// Kept from one frame to the next, see `Graphics::record_gif`
+    pub recorder: Option<GifRecorder>,
+    /// See `Graphics::take_capture_errors`
+    pub capture_errors: Vec<(PathBuf, CaptureError)>,
 }
 
 impl RendererData {
     pub fn new() -> Self {
         Self {
             draw_calls: Vec::with_capacity(100),
+            transparent: Vec::new(),
             draw_calls_binding: Vec::with_capacity(100),
             draw_calls_count: 0,
             view_proj: Mat4::IDENTITY,
+            camera_transform: Mat4::IDENTITY,
             mode: Mode::Mode3d,
             viewport: Rect2d {
                 position: vec2(0.0, 0.0),
                 size: vec2(1.0, 1.0)
             },
             background: Color::black(),
+            fog: None,
+            light: Light::new(),
+            skies: Vec::new(),
+            debug_lines: Vec::new(),
+            timed_lines: Vec::new(),
+            canvas: None,
+            render_mode: RenderMode::Shaded,
+            target: None,
+            resolution: Resolution::DEFAULT,
+            screenshots: Vec::new(),
+            recorder: None,
+            capture_errors: Vec::new(),
         }
     }
 
     pub fn begin_frame(&mut self) {
+        // the unused draw calls must not keep the textures of past frames alive
+        for draw in self.draw_calls.iter_mut().skip(self.draw_calls_count) {
+            draw.material.texture = None;
+            draw.target = None;
+        }
         self.draw_calls_count = 0;
+        self.transparent.clear();
+        self.skies.clear();
+        self.debug_lines.clear();
+        let now = miniquad::date::now();
+        self.timed_lines.retain(|line| line.until > now);
     }
 }
 
@@ -60,45 +182,135 @@ pub struct DrawCall {
     pub mode: Mode,
     pub viewport: Rect2d,
     pub background: Color,
+    pub camera_position: Vec3,
+    pub fog: Option<Fog>,
+    pub material: Material,
+    pub render_mode: RenderMode,
+    /// Drawn into the image when `None`
+    pub target: Option<Texture>,
 }
 
-/// The console renderer
-pub struct Renderer {
-    screen_res: UVec2,
-    display_pipeline: Pipeline,
-    display_bind: Bindings,
-    pipelines: [Pipeline; 4],
-    offscreen_pass: RenderPass,
-    ctx: Box<dyn RenderingBackend>,
-    egui_mq: egui_miniquad::EguiMq,
+pub struct TransparentDraw {
+    /// Distance to the camera, the furthest are drawn first
+    pub distance: f32,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<i32>,
+    pub transform: Mat4,
+    pub primitive: Primitive,
+    pub material: Material,
 }
 
-impl Renderer {
-    const TRIANGLES_PIPELINE_3D: usize = 0;
-    const LINES_PIPELINE_3D: usize = 1;
-    const TRIANGLES_PIPELINE_2D: usize = 2;
-    const LINES_PIPELINE_2D: usize = 3;
+pub struct SkyDraw {
+    /// Index of the first draw call of the camera, the sky is drawn just before it
+    pub before: usize,
+    pub sky: Sky,
+    pub inverse_view_proj: Mat4,
+    pub viewport: Rect2d,
+    pub target: Option<Texture>,
+}
 
-    pub fn new() -> Renderer {
-        let mut ctx: Box<dyn RenderingBackend> = window::new_rendering_backend();
+/// Depth texture rendered from the light, the depth is packed in the color channels
+#[derive(Clone, Copy)]
+struct ShadowMap {
+    resolution: u32,
+    texture: TextureId,
+    pass: RenderPass,
+}
 
-        let color_img = ctx.new_render_texture(TextureParams {
-            width: IMAGE_RES.x,
-            height: IMAGE_RES.y,
+impl ShadowMap {
+    fn new(ctx: &mut dyn RenderingBackend, resolution: u32) -> Self {
+        let texture = ctx.new_render_texture(TextureParams {
+            width: resolution,
+            height: resolution,
             format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
             min_filter: FilterMode::Nearest,
             mag_filter: FilterMode::Nearest,
             ..Default::default()
         });
-        let depth_img = ctx.new_render_texture(TextureParams {
-            width: IMAGE_RES.x,
-            height: IMAGE_RES.y,
+        let depth = ctx.new_render_texture(TextureParams {
+            width: resolution,
+            height: resolution,
             format: TextureFormat::Depth,
             ..Default::default()
         });
+        Self {
+            resolution,
+            texture,
+            pass: ctx.new_render_pass(texture, Some(depth)),
+        }
+    }
+}
 
-        // offscreen
-        let offscreen_pass = ctx.new_render_pass(color_img, Some(depth_img));
+/// Copy of a `Texture` on the GPU
+struct GpuTexture {
+    id: TextureId,
+    version: u64,
+    lifetime: Weak<()>,
+}
+
+/// Light and shadow shared by the passes of a frame
+struct FrameLighting {
+    light: Light,
+    light_view_proj: Mat4,
+    shadow_map: Option<TextureId>,
+    shadow_params: [f32; 4],
+}
+
+/// Render states of a pipeline, they are created on first use
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    mode: Mode,
+    primitive: Primitive,
+    blend: BlendMode,
+    cull: CullMode,
+    /// Additive blending without depth test, see `RenderMode::Overdraw`
+    overdraw: bool,
+}
+
+/// The console renderer
+pub struct Renderer {
+    screen_res: UVec2,
+    display_pipeline: Pipeline,
+    display_bind: Bindings,
+    /// Size of the offscreen image
+    image_res: UVec2,
+    shader_3d: ShaderId,
+    shader_2d: ShaderId,
+    pipelines: HashMap<PipelineKey, Pipeline>,
+    textures: HashMap<u64, GpuTexture>,
+    white_texture: TextureId,
+    shadow_pipeline: Pipeline,
+    shadow_map: Option<ShadowMap>,
+    sky_pipeline: Pipeline,
+    sky_bindings: Bindings,
+    cubemaps: HashMap<u64, (TextureId, Weak<()>)>,
+    white_cubemap: TextureId,
+    offscreen_pass: RenderPass,
+    render_targets: HashMap<u64, (RenderPass, Weak<()>)>,
+    ctx: Box<dyn RenderingBackend>,
+    egui_mq: egui_miniquad::EguiMq,
+}
+
+impl Renderer {
+    /// Distance mapped to black in `RenderMode::Depth`
+    const DEPTH_VIEW_RANGE: f32 = 50.0;
+    const DRAW_CALL_COLORS: [[f32; 4]; 8] = [
+        [0.9, 0.2, 0.2, 1.0],
+        [0.2, 0.9, 0.2, 1.0],
+        [0.2, 0.4, 0.9, 1.0],
+        [0.9, 0.9, 0.2, 1.0],
+        [0.9, 0.2, 0.9, 1.0],
+        [0.2, 0.9, 0.9, 1.0],
+        [0.9, 0.5, 0.1, 1.0],
+        [0.6, 0.3, 0.9, 1.0],
+    ];
+
+    pub fn new(image_res: UVec2) -> Renderer {
+        let mut ctx: Box<dyn RenderingBackend> = window::new_rendering_backend();
+
+        let offscreen_pass = Self::new_offscreen_pass(&mut *ctx, image_res);
+        let color_img = ctx.render_pass_texture(offscreen_pass);
 
         let shader_3d = ctx
             .new_shader(
@@ -120,14 +332,27 @@ impl Renderer {
             )
             .unwrap();
 
-        let triangles_pipeline_3d = ctx.new_pipeline_with_params(
+        let white_texture = ctx.new_texture_from_rgba8(1, 1, &[255, 255, 255, 255]);
+
+        let shadow_shader = ctx
+            .new_shader(
+                ShaderSource::Glsl {
+                    vertex: shadow_shader::VERTEX,
+                    fragment: shadow_shader::FRAGMENT,
+                },
+                shadow_shader::meta(),
+            )
+            .unwrap();
+
+        let shadow_pipeline = ctx.new_pipeline_with_params(
             &[BufferLayout::default()],
             &[
                 VertexAttribute::new("in_pos", VertexFormat::Float3),
                 VertexAttribute::new("in_color", VertexFormat::Float4),
                 VertexAttribute::new("in_normal", VertexFormat::Float3),
+                VertexAttribute::new("in_uv", VertexFormat::Float2),
             ],
-            shader_3d,
+            shadow_shader,
             PipelineParams {
                 primitive_type: PrimitiveType::Triangles,
                 depth_write: true,
@@ -136,56 +361,54 @@ impl Renderer {
             }
         );
 
-        let lines_pipeline_3d = ctx.new_pipeline_with_params(
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("in_pos", VertexFormat::Float3),
-                VertexAttribute::new("in_color", VertexFormat::Float4),
-                VertexAttribute::new("in_normal", VertexFormat::Float3),
-            ],
-            shader_3d,
-            PipelineParams {
-                primitive_type: PrimitiveType::Lines,
-                ..Default::default()
-            }
-        );
+        let sky_shader = ctx
+            .new_shader(
+                ShaderSource::Glsl {
+                    vertex: sky_shader::VERTEX,
+                    fragment: sky_shader::FRAGMENT,
+                },
+                sky_shader::meta(),
+            )
+            .unwrap();
 
-        let triangles_pipeline_2d = ctx.new_pipeline_with_params(
+        let sky_pipeline = ctx.new_pipeline_with_params(
             &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("in_pos", VertexFormat::Float3),
-                VertexAttribute::new("in_color", VertexFormat::Float4),
-                VertexAttribute::new("in_normal", VertexFormat::Float3),
-            ],
-            shader_2d,
+            &[VertexAttribute::new("in_pos", VertexFormat::Float2)],
+            sky_shader,
             PipelineParams {
-                primitive_type: PrimitiveType::Triangles,
-                depth_write: true,
-                depth_test: Comparison::LessOrEqual,
+                depth_write: false,
+                depth_test: Comparison::Always,
                 ..Default::default()
             }
         );
 
-        let lines_pipeline_2d = ctx.new_pipeline_with_params(
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("in_pos", VertexFormat::Float3),
-                VertexAttribute::new("in_color", VertexFormat::Float4),
-                VertexAttribute::new("in_normal", VertexFormat::Float3),
-            ],
-            shader_2d,
-            PipelineParams {
-                primitive_type: PrimitiveType::Lines,
+        // a single triangle covering the viewport
+        let sky_vertices = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0] as [f32; 6];
+        let white_face: &[&[u8]] = &[&[255; 4]];
+        let white_cubemap = ctx.new_texture(
+            TextureAccess::Static,
+            TextureSource::Array(&[white_face; 6]),
+            TextureParams {
+                kind: TextureKind::CubeMap,
+                width: 1,
+                height: 1,
+                format: TextureFormat::RGBA8,
                 ..Default::default()
-            }
+            },
         );
-
-        let pipelines = [
-            triangles_pipeline_3d, 
-            lines_pipeline_3d,
-            triangles_pipeline_2d, 
-            lines_pipeline_2d,
-        ];
+        let sky_bindings = Bindings {
+            vertex_buffers: vec![ctx.new_buffer(
+                BufferType::VertexBuffer,
+                BufferUsage::Immutable,
+                BufferSource::slice(&sky_vertices),
+            )],
+            index_buffer: ctx.new_buffer(
+                BufferType::IndexBuffer,
+                BufferUsage::Immutable,
+                BufferSource::slice(&[0u16, 1, 2]),
+            ),
+            images: vec![white_cubemap],
+        };
 
         // display pass
         let quad_vertices = [
@@ -232,11 +455,23 @@ impl Renderer {
         );
 
         Renderer {
-            screen_res: IMAGE_RES,
+            screen_res: image_res,
+            image_res,
             display_pipeline,
             display_bind,
-            pipelines,
+            shader_3d,
+            shader_2d,
+            pipelines: HashMap::new(),
+            textures: HashMap::new(),
+            white_texture,
+            shadow_pipeline,
+            shadow_map: None,
+            sky_pipeline,
+            sky_bindings,
+            cubemaps: HashMap::new(),
+            white_cubemap,
             offscreen_pass,
+            render_targets: HashMap::new(),
             egui_mq: egui_miniquad::EguiMq::new(&mut *ctx),
             ctx,
         }
@@ -247,6 +482,15 @@ impl Renderer {
     }
 
     pub fn draw(&mut self, data: &mut RendererData) {
+        let image_res = data.resolution.size;
+        if image_res != self.image_res {
+            self.ctx.delete_render_pass(self.offscreen_pass);
+            self.offscreen_pass = Self::new_offscreen_pass(&mut *self.ctx, image_res);
+            self.display_bind.images[0] = self.ctx.render_pass_texture(self.offscreen_pass);
+            self.image_res = image_res;
+        }
+        self.release_dropped_textures();
+
         for _ in 0..data.draw_calls.len() - data.draw_calls_binding.len() {
             let vertex_buffer = self.ctx.new_buffer(
                 BufferType::VertexBuffer,
@@ -263,25 +507,30 @@ impl Renderer {
             let bindings = Bindings {
                 vertex_buffers: vec![vertex_buffer],
                 index_buffer,
-                images: vec![],
+                images: vec![self.white_texture, self.white_texture],
             };
 
             data.draw_calls_binding.push(bindings);
         }
 
-        // offscreen pass
-        self.ctx.begin_pass(
-            Some(self.offscreen_pass),
-            PassAction::clear_color(
-                0.0, 0.0, 0.0, 0.0
-            ),
-        );
-
-        let mut previous_background = Color::black();
-
+        // upload the geometry, used by the shadow and offscreen passes
         for (draw, bindings) in data.draw_calls
             .iter()
-            .zip(data.draw_calls_binding.iter()) {
+            .zip(data.draw_calls_binding.iter_mut())
+            .take(data.draw_calls_count) {
+                Self::reserve_buffer::<Vertex>(
+                    &mut *self.ctx,
+                    &mut bindings.vertex_buffers[0],
+                    BufferType::VertexBuffer,
+                    draw.vertices.len(),
+                );
+                Self::reserve_buffer::<i32>(
+                    &mut *self.ctx,
+                    &mut bindings.index_buffer,
+                    BufferType::IndexBuffer,
+                    draw.indices.len(),
+                );
+
                 self.ctx.buffer_update(
                     bindings.vertex_buffers[0], 
                     BufferSource::slice(&draw.vertices)
@@ -292,30 +541,94 @@ impl Renderer {
                     BufferSource::slice(&draw.indices)
                 );
 
-                self.ctx.apply_pipeline(
-                    &self.get_pipeline(draw.primitive, draw.mode)
-                );
-                self.ctx.apply_bindings(bindings);
-
-                let vs_params = shader_3d::Uniforms {
-                    model: draw.model,
-                    view_proj: draw.view_proj,
+                bindings.images[0] = match &draw.material.texture {
+                    // sampling the target being drawn would be a feedback loop
+                    Some(texture) if draw.target.as_ref() == Some(texture) => self.white_texture,
+                    Some(texture) if texture.is_render_target() => {
+                        let pass = self.render_target_pass(texture);
+                        self.ctx.render_pass_texture(pass)
+                    },
+                    Some(texture) => self.upload_texture(texture),
+                    None => self.white_texture,
                 };
-                self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
+        }
 
-                self.ctx.apply_scissor_rect(
-                    (draw.viewport.position.x * IMAGE_RES.x as f32) as i32, 
-                    (draw.viewport.position.y * IMAGE_RES.y as f32) as i32,
-                    (draw.viewport.size.x * IMAGE_RES.x as f32) as i32, 
-                    (draw.viewport.size.y * IMAGE_RES.y as f32) as i32,
-                );
-                self.ctx.apply_viewport(
-                    (draw.viewport.position.x * IMAGE_RES.x as f32) as i32, 
-                    (draw.viewport.position.y * IMAGE_RES.y as f32) as i32,
-                    (draw.viewport.size.x * IMAGE_RES.x as f32) as i32, 
-                    (draw.viewport.size.y * IMAGE_RES.y as f32) as i32,
-                );
-                let background = draw.background;
+        let light = data.light;
+        let light_view_proj = light.shadow_view_proj().unwrap_or(Mat4::IDENTITY);
+        let lighting = FrameLighting {
+            light,
+            light_view_proj,
+            shadow_map: light.shadow().map(|shadow| self.draw_shadow_map(data, shadow, light_view_proj)),
+            shadow_params: match light.shadow() {
+                Some(shadow) => [1.0, shadow.bias, 1.0 / shadow.resolution as f32, shadow.soft as u8 as f32],
+                None => [0.0; 4],
+            },
+        };
+
+        // the render targets first, the image can show them
+        let mut targets: Vec<Texture> = Vec::new();
+        for target in data.draw_calls
+            .iter()
+            .take(data.draw_calls_count)
+            .filter_map(|draw| draw.target.as_ref())
+            .chain(data.skies.iter().filter_map(|sky| sky.target.as_ref())) {
+                if !targets.contains(target) {
+                    targets.push(target.clone());
+                }
+        }
+        for target in &targets {
+            self.draw_pass(data, Some(target), &lighting);
+        }
+        self.draw_pass(data, None, &lighting);
+        self.capture(data);
+
+        // draw to fullscreen quad
+        {
+            // display pass
+            self.ctx.begin_default_pass(Default::default());
+            self.ctx.apply_pipeline(&self.display_pipeline);
+            self.ctx.apply_bindings(&self.display_bind);
+            let scale = display_scale(self.screen_res.as_vec2(), &data.resolution);
+            let vs_params = display_shader::Uniforms {
+                model: Mat4::from_scale(scale.extend(1.0)),
+            };
+            self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
+            self.ctx.draw(0, 6, 1);
+            self.ctx.end_render_pass();
+        }
+    }
+
+    /// Draws the cameras targeting `target`, or the image when `None`
+    fn draw_pass(&mut self, data: &mut RendererData, target: Option<&Texture>, lighting: &FrameLighting) {
+        let (pass, size) = match target {
+            Some(target) => (self.render_target_pass(target), target.size()),
+            None => (self.offscreen_pass, self.image_res),
+        };
+        self.ctx.begin_pass(
+            Some(pass),
+            PassAction::clear_color(
+                0.0, 0.0, 0.0, 0.0
+            ),
+        );
+
+        let mut previous_background = Color::black();
+        let mut skies = data.skies
+            .iter()
+            .filter(|sky| sky.target.as_ref() == target && data.render_mode != RenderMode::Overdraw)
+            .peekable();
+
+        for (index, (draw, bindings)) in data.draw_calls
+            .iter()
+            .zip(data.draw_calls_binding.iter_mut())
+            .take(data.draw_calls_count)
+            .enumerate()
+            .filter(|(_, (draw, _))| draw.target.as_ref() == target) {
+                self.apply_viewport_rect(&draw.viewport, size);
+                let background = if data.render_mode == RenderMode::Overdraw {
+                    Color::black()
+                } else {
+                    draw.background
+                };
                 if previous_background != background {
                     self.ctx.clear(
                         Some((
@@ -329,37 +642,69 @@ impl Renderer {
                     );
                     previous_background = background;
                 }
+
+                // behind the geometry of its camera
+                while let Some(sky) = skies.next_if(|sky| sky.before <= index) {
+                    self.draw_sky(sky, size);
+                    self.apply_viewport_rect(&draw.viewport, size);
+                }
+
+                bindings.images[1] = lighting.shadow_map.unwrap_or(self.white_texture);
+
+                let pipeline = self.pipeline(PipelineKey {
+                    mode: draw.mode,
+                    primitive: draw.primitive,
+                    blend: draw.material.blend,
+                    cull: match (target, draw.material.cull) {
+                        // drawn upside down, see `Graphics::set_camera`
+                        (Some(_), CullMode::Back) => CullMode::Front,
+                        (Some(_), CullMode::Front) => CullMode::Back,
+                        (_, cull) => cull,
+                    },
+                    overdraw: draw.mode == Mode::Mode3d && draw.render_mode == RenderMode::Overdraw,
+                });
+                self.ctx.apply_pipeline(&pipeline);
+                self.ctx.apply_bindings(bindings);
+
+                match draw.mode {
+                    Mode::Mode3d => {
+                        let (fog_color, fog_params, depth_cue) = Self::fog_uniforms(draw.fog);
+                        let (debug_params, debug_color) = Self::debug_uniforms(draw.render_mode, index);
+                        let vs_params = shader_3d::Uniforms {
+                            model: draw.model,
+                            view_proj: draw.view_proj,
+                            light_view_proj: lighting.light_view_proj,
+                            camera_position: draw.camera_position,
+                            fog_color,
+                            fog_params,
+                            depth_cue,
+                            light_direction: lighting.light.direction(),
+                            shadow_params: lighting.shadow_params,
+                            shading_params: Self::shading_uniforms(draw.material.shading),
+                            emissive: draw.material.emissive.as_array(),
+                            debug_params,
+                            debug_color,
+                        };
+                        self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
+                    },
+                    Mode::Mode2d => {
+                        let vs_params = shader_2d::Uniforms {
+                            model: draw.model,
+                            view_proj: draw.view_proj,
+                        };
+                        self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
+                    },
+                }
+
                 self.ctx.draw(0, draw.indices.len() as i32, 1);
         }
 
-        self.ctx.end_render_pass();
-
-        // draw to fullscreen quad
-        {
-            // display pass
-            self.ctx.begin_default_pass(Default::default());
-            self.ctx.apply_pipeline(&self.display_pipeline);
-            self.ctx.apply_bindings(&self.display_bind);
-            let scale = if self.screen_res.x as f32 / self.screen_res.y as f32 > IMAGE_RATIO_XY {
-                vec3(
-                    (self.screen_res.y as f32 / self.screen_res.x as f32) * IMAGE_RATIO_XY,
-                    1.0, 
-                    1.0
-                )
-            } else {
-                vec3(
-                    1.0,  
-                    (self.screen_res.x as f32 / self.screen_res.y as f32) * IMAGE_RATIO_YX,
-                    1.0
-                )
-            };
-            let vs_params = display_shader::Uniforms {
-                model: Mat4::from_scale(scale),
-            };
-            self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
-            self.ctx.draw(0, 6, 1);
-            self.ctx.end_render_pass();
+        // cameras without geometry
+        for sky in skies {
+            self.draw_sky(sky, size);
         }
+
+        self.ctx.end_render_pass();
     }
 
     pub fn draw_ui(&mut self, gui: &mut Gui) {
@@ -392,55 +737,715 @@ impl Renderer {
         &mut self.egui_mq
     }
 
-    fn get_pipeline(&self, primitive: Primitive, mode: Mode) -> Pipeline {
-        match mode {
-            Mode::Mode3d => {
-                match primitive {
-                    Primitive::Lines => self.pipelines[Self::LINES_PIPELINE_3D],
-                    Primitive::Triangles => self.pipelines[Self::TRIANGLES_PIPELINE_3D],
+    /// Grows a buffer to hold `count` elements of `T`, which is also the index type of an index buffer
+    fn reserve_buffer<T>(ctx: &mut dyn RenderingBackend, buffer: &mut BufferId, buffer_type: BufferType, count: usize) {
+        let Some(source) = grown_buffer::<T>(ctx.buffer_size(*buffer), count) else {
+            return;
+        };
+
+        ctx.delete_buffer(*buffer);
+        *buffer = ctx.new_buffer(
+            buffer_type,
+            BufferUsage::Immutable,
+            source,
+        );
+    }
+
+    /// Color and depth images of the given size, drawn to the window by the display pass
+    fn new_offscreen_pass(ctx: &mut dyn RenderingBackend, size: UVec2) -> RenderPass {
+        let color_img = ctx.new_render_texture(TextureParams {
+            width: size.x,
+            height: size.y,
+            format: TextureFormat::RGBA8,
+            min_filter: FilterMode::Nearest,
+            mag_filter: FilterMode::Nearest,
+            ..Default::default()
+        });
+        let depth_img = ctx.new_render_texture(TextureParams {
+            width: size.x,
+            height: size.y,
+            format: TextureFormat::Depth,
+            ..Default::default()
+        });
+        ctx.new_render_pass(color_img, Some(depth_img))
+    }
+
+    fn apply_viewport_rect(&mut self, viewport: &Rect2d, size: UVec2) {
+        let size = size.as_vec2();
+        let x = (viewport.position.x * size.x) as i32;
+        let y = (viewport.position.y * size.y) as i32;
+        let width = (viewport.size.x * size.x) as i32;
+        let height = (viewport.size.y * size.y) as i32;
+        self.ctx.apply_scissor_rect(x, y, width, height);
+        self.ctx.apply_viewport(x, y, width, height);
+    }
+
+    fn draw_sky(&mut self, sky: &SkyDraw, size: UVec2) {
+        let mut uniforms = sky_shader::Uniforms {
+            inverse_view_proj: sky.inverse_view_proj,
+            sky_params: [0.0; 4],
+            zenith_color: [0.0; 4],
+            horizon_color: [0.0; 4],
+            ground_color: [0.0; 4],
+            sun_direction: [0.0; 4],
+            sun_color: [0.0; 4],
+        };
+        self.sky_bindings.images[0] = self.white_cubemap;
+        match &sky.sky {
+            Sky::Gradient { zenith, horizon, ground, sun } => {
+                uniforms.zenith_color = zenith.as_array();
+                uniforms.horizon_color = horizon.as_array();
+                uniforms.ground_color = ground.as_array();
+                if let Some(sun) = sun {
+                    uniforms.sky_params[1] = sun.radius.cos();
+                    uniforms.sun_direction = sun.direction.extend(0.0).to_array();
+                    // the alpha tells the shader there is a sun
+                    uniforms.sun_color = [sun.color.r, sun.color.g, sun.color.b, 1.0];
                 }
             },
-            Mode::Mode2d => {
-                match primitive {
-                    Primitive::Lines => self.pipelines[Self::LINES_PIPELINE_2D],
-                    Primitive::Triangles => self.pipelines[Self::TRIANGLES_PIPELINE_2D],
+            Sky::Starfield { background, density, seed } => {
+                uniforms.sky_params = [1.0, 0.0, *density, (*seed % 1000) as f32];
+                uniforms.zenith_color = background.as_array();
+            },
+            Sky::Cubemap(cubemap) => {
+                uniforms.sky_params[0] = 2.0;
+                self.sky_bindings.images[0] = self.upload_cubemap(cubemap);
+            },
+        }
+
+        self.apply_viewport_rect(&sky.viewport, size);
+        self.ctx.apply_pipeline(&self.sky_pipeline);
+        self.ctx.apply_bindings(&self.sky_bindings);
+        self.ctx.apply_uniforms(UniformsSource::table(&uniforms));
+        self.ctx.draw(0, 3, 1);
+    }
+
+    /// Renders the depth of the opaque 3D geometry seen from the light
+    fn draw_shadow_map(&mut self, data: &RendererData, shadow: &Shadow, light_view_proj: Mat4) -> TextureId {
+        let shadow_map = match self.shadow_map {
+            Some(shadow_map) if shadow_map.resolution == shadow.resolution => shadow_map,
+            previous => {
+                if let Some(previous) = previous {
+                    self.ctx.delete_render_pass(previous.pass);
+                }
+                let shadow_map = ShadowMap::new(&mut *self.ctx, shadow.resolution);
+                self.shadow_map = Some(shadow_map);
+                shadow_map
+            }
+        };
+
+        // white is the farthest depth
+        self.ctx.begin_pass(Some(shadow_map.pass), PassAction::clear_color(1.0, 1.0, 1.0, 1.0));
+        self.ctx.apply_pipeline(&self.shadow_pipeline);
+        let size = shadow.resolution as i32;
+        self.ctx.apply_viewport(0, 0, size, size);
+        self.ctx.apply_scissor_rect(0, 0, size, size);
+
+        for (draw, bindings) in data.draw_calls
+            .iter()
+            .zip(data.draw_calls_binding.iter())
+            .take(data.draw_calls_count) {
+                if draw.mode != Mode::Mode3d || draw.primitive != Primitive::Triangles || draw.material.is_transparent() {
+                    continue;
                 }
+                self.ctx.apply_bindings(bindings);
+                let vs_params = shadow_shader::Uniforms {
+                    model: draw.model,
+                    light_view_proj,
+                };
+                self.ctx.apply_uniforms(UniformsSource::table(&vs_params));
+                self.ctx.draw(0, draw.indices.len() as i32, 1);
+        }
+
+        self.ctx.end_render_pass();
+        shadow_map.texture
+    }
+
+    /// Shading packed as (kind, specular, shininess, unused)
+    fn shading_uniforms(shading: Shading) -> [f32; 4] {
+        match shading {
+            Shading::Flat => [0.0; 4],
+            Shading::Gouraud => [1.0, 0.0, 0.0, 0.0],
+            Shading::Lambert => [2.0, 0.0, 0.0, 0.0],
+            Shading::BlinnPhong { specular, shininess } => [3.0, specular, shininess, 0.0],
+            Shading::Unlit => [4.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Render mode packed as (kind, depth range, unused, unused) and the draw call color
+    fn debug_uniforms(render_mode: RenderMode, index: usize) -> ([f32; 4], [f32; 4]) {
+        match render_mode {
+            // the wireframe is drawn by `Graphics::draw_object`
+            RenderMode::Shaded | RenderMode::Wireframe => ([0.0; 4], [0.0; 4]),
+            RenderMode::Normals => ([1.0, 0.0, 0.0, 0.0], [0.0; 4]),
+            RenderMode::Depth => ([2.0, Self::DEPTH_VIEW_RANGE, 0.0, 0.0], [0.0; 4]),
+            RenderMode::Overdraw => ([3.0, 0.0, 0.0, 0.0], [0.0; 4]),
+            RenderMode::DrawCalls => ([4.0, 0.0, 0.0, 0.0], Self::DRAW_CALL_COLORS[index % Self::DRAW_CALL_COLORS.len()]),
+        }
+    }
+
+    /// Fog color, falloff packed as (kind, start, end, density) and depth cue
+    fn fog_uniforms(fog: Option<Fog>) -> ([f32; 4], [f32; 4], f32) {
+        let Some(fog) = fog else {
+            return ([0.0; 4], [0.0; 4], 0.0);
+        };
+        let params = match fog.falloff {
+            FogFalloff::Linear { start, end } => [1.0, start, end, 0.0],
+            FogFalloff::Exponential { density } => [2.0, 0.0, 0.0, density],
+            FogFalloff::ExponentialSquared { density } => [3.0, 0.0, 0.0, density],
+        };
+        (fog.color.unwrap_or(Color::black()).as_array(), params, fog.depth_cue)
+    }
+
+    /// Saves the requested screenshots and records the image for the GIF,
+    /// only reads the image back when one of them needs it
+    fn capture(&mut self, data: &mut RendererData) {
+        let time = miniquad::date::now();
+        let record = data.recorder.as_ref().is_some_and(|recorder| recorder.wants_frame(time));
+        if data.screenshots.is_empty() && !record {
+            return;
+        }
+
+        let image = self.read_image();
+        for screenshot in data.screenshots.drain(..) {
+            if let Err(err) = save_png(&image, screenshot.scale, &screenshot.path) {
+                data.capture_errors.push((screenshot.path, err));
+            }
+        }
+        if let Some(recorder) = data.recorder.as_mut().filter(|_| record) {
+            recorder.push(&image, time);
+        }
+    }
+
+    /// Pixels of the image as shown on the display, before being scaled to the window
+    fn read_image(&mut self) -> Texture {
+        let size = self.image_res;
+        let mut pixels = vec![0; (size.x * size.y * 4) as usize];
+        let texture = self.ctx.render_pass_texture(self.offscreen_pass);
+        self.ctx.texture_read_pixels(texture, &mut pixels);
+        // stored bottom up, and drawn opaque over the black borders
+        let mut image: Vec<u8> = pixels.chunks_exact((size.x * 4) as usize).rev().flatten().copied().collect();
+        for pixel in image.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        Texture::new(size.x, size.y, image)
+    }
+
+    /// Pass drawing into a render target, created on first use
+    fn render_target_pass(&mut self, target: &Texture) -> RenderPass {
+        self.render_targets
+            .entry(target.id())
+            .or_insert_with(|| (Self::new_offscreen_pass(&mut *self.ctx, target.size()), target.lifetime()))
+            .0
+    }
+
+    /// GPU copy of a texture, uploaded again when its pixels changed
+    fn upload_texture(&mut self, texture: &Texture) -> TextureId {
+        match self.textures.get_mut(&texture.id()) {
+            Some(gpu) if gpu.version == texture.version() => gpu.id,
+            Some(gpu) if self.ctx.texture_size(gpu.id) == (texture.width(), texture.height()) => {
+                self.ctx.texture_update(gpu.id, texture.pixels());
+                gpu.version = texture.version();
+                gpu.id
+            }
+            previous => {
+                if let Some(gpu) = previous {
+                    self.ctx.delete_texture(gpu.id);
+                }
+                let id = self.ctx.new_texture(
+                    TextureAccess::Static,
+                    TextureSource::Bytes(texture.pixels()),
+                    TextureParams {
+                        width: texture.width(),
+                        height: texture.height(),
+                        format: TextureFormat::RGBA8,
+                        wrap: TextureWrap::Repeat,
+                        min_filter: FilterMode::Nearest,
+                        mag_filter: FilterMode::Nearest,
+                        ..Default::default()
+                    },
+                );
+                self.textures.insert(texture.id(), GpuTexture {
+                    id,
+                    version: texture.version(),
+                    lifetime: texture.lifetime(),
+                });
+                id
+            }
+        }
+    }
+
+    /// Deletes the GPU copies of the textures, cubemaps and render targets dropped by the game
+    fn release_dropped_textures(&mut self) {
+        let ctx = &mut self.ctx;
+        self.textures.retain(|_, gpu| {
+            let alive = gpu.lifetime.strong_count() > 0;
+            if !alive {
+                ctx.delete_texture(gpu.id);
+            }
+            alive
+        });
+        self.cubemaps.retain(|_, (id, lifetime)| {
+            let alive = lifetime.strong_count() > 0;
+            if !alive {
+                ctx.delete_texture(*id);
+            }
+            alive
+        });
+        // the pass owns its color and depth textures
+        self.render_targets.retain(|_, (pass, lifetime)| {
+            let alive = lifetime.strong_count() > 0;
+            if !alive {
+                ctx.delete_render_pass(*pass);
+            }
+            alive
+        });
+    }
+
+    /// Cubemaps can't be modified, they are uploaded once
+    fn upload_cubemap(&mut self, cubemap: &Cubemap) -> TextureId {
+        if let Some(&(id, _)) = self.cubemaps.get(&cubemap.id()) {
+            return id;
+        }
+        let levels: Vec<[&[u8]; 1]> = cubemap.faces().iter().map(|face| [face.pixels()]).collect();
+        let faces: Vec<&[&[u8]]> = levels.iter().map(|level| &level[..]).collect();
+        let id = self.ctx.new_texture(
+            TextureAccess::Static,
+            TextureSource::Array(&faces),
+            TextureParams {
+                kind: TextureKind::CubeMap,
+                width: cubemap.size(),
+                height: cubemap.size(),
+                format: TextureFormat::RGBA8,
+                wrap: TextureWrap::Clamp,
+                min_filter: FilterMode::Nearest,
+                mag_filter: FilterMode::Nearest,
+                ..Default::default()
             },
+        );
+        self.cubemaps.insert(cubemap.id(), (id, cubemap.lifetime()));
+        id
+    }
+
+    fn pipeline(&mut self, key: PipelineKey) -> Pipeline {
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return *pipeline;
         }
-        
+
+        let shader = match key.mode {
+            Mode::Mode3d => self.shader_3d,
+            Mode::Mode2d => self.shader_2d,
+        };
+        let color_blend = match key.blend {
+            _ if key.overdraw => BlendState::new(
+                Equation::Add,
+                BlendFactor::One,
+                BlendFactor::One,
+            ),
+            BlendMode::Additive => BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One,
+            ),
+            BlendMode::Opaque | BlendMode::Alpha => BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+            ),
+        };
+        let params = match key.primitive {
+            Primitive::Triangles => PipelineParams {
+                primitive_type: PrimitiveType::Triangles,
+                // transparent geometry is sorted instead
+                depth_write: key.blend == BlendMode::Opaque && !key.overdraw,
+                depth_test: if key.overdraw { Comparison::Always } else { Comparison::LessOrEqual },
+                cull_face: match key.cull {
+                    CullMode::None => CullFace::Nothing,
+                    CullMode::Back => CullFace::Back,
+                    CullMode::Front => CullFace::Front,
+                },
+                color_blend: Some(color_blend),
+                ..Default::default()
+            },
+            Primitive::Lines => PipelineParams {
+                primitive_type: PrimitiveType::Lines,
+                color_blend: Some(color_blend),
+                ..Default::default()
+            },
+        };
+
+        let pipeline = self.ctx.new_pipeline_with_params(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("in_pos", VertexFormat::Float3),
+                VertexAttribute::new("in_color", VertexFormat::Float4),
+                VertexAttribute::new("in_normal", VertexFormat::Float3),
+                VertexAttribute::new("in_uv", VertexFormat::Float2),
+            ],
+            shader,
+            params,
+        );
+        self.pipelines.insert(key, pipeline);
+        pipeline
     }
 }
 
 mod shader_3d {
-    use glam::Mat4;
+    use glam::{Mat4, Vec3};
     use miniquad::*;
 
-    pub const VERTEX: &str = r#"#version 140
+    /// Share of the fog color at a distance, see `Fog::amount`
+    macro_rules! fog_amount {
+        () => {
+            r#"
+        uniform vec4 fog_params;
+
+        float fog_amount(float distance) {
+            if (fog_params.x == 1.0) {
+                return clamp((distance - fog_params.y) / max(fog_params.z - fog_params.y, 0.0001), 0.0, 1.0);
+            } else if (fog_params.x == 2.0) {
+                return 1.0 - exp(-fog_params.w * distance);
+            } else if (fog_params.x == 3.0) {
+                float d = fog_params.w * distance;
+                return 1.0 - exp(-d * d);
+            }
+            return 0.0;
+        }
+"#
+        };
+    }
+
+    pub const VERTEX: &str = concat!(r#"#version 140
         in vec3 in_pos;
         in vec4 in_color;
         in vec3 in_normal;
+        in vec2 in_uv;
 
         uniform mat4 model;
         uniform mat4 view_proj;
+        uniform vec3 camera_position;
+        uniform float depth_cue;
+        uniform vec3 light_direction;
+        uniform mat4 light_view_proj;
 
-        flat out lowp vec4 polygon_color;
+        // kind, specular, shininess
+        uniform vec4 shading_params;
 
+        flat out lowp vec4 polygon_color;
+        // color without the direct light, where the polygon is in shadow
+        flat out lowp vec4 shadow_color;
+        // Gouraud shading
+        out vec4 vertex_color;
+        out vec4 vertex_shadow_color;
+        // per pixel shading
+        out vec4 base_color;
+        out vec3 world_normal;
+        out vec3 world_position;
+        out vec2 uv;
+        out float fog_distance;
+        out vec4 light_space;
+"#, fog_amount!(), r#"
         void main() {
+            uv = in_uv;
+            vec4 world_pos = model * vec4(in_pos, 1.0);
+            world_position = world_pos.xyz;
+            fog_distance = length(world_pos.xyz - camera_position);
             vec3 light_color = vec3(1.0);
-            vec3 light_dir = normalize(-vec3(-1.0, -1.0, -1.0));
+            vec3 light_dir = normalize(-light_direction);
             vec3 ambient = 0.2 * light_color;
-            vec3 world_normal = mat3(transpose(inverse(model))) * in_normal;  
-            vec3 diffuse = max(dot(world_normal, light_dir), 0.0) * light_color;
-            polygon_color = vec4((ambient + diffuse) * in_color.xyz, 1.0);
-            gl_Position = view_proj * model * vec4(in_pos, 1.0);
+            world_normal = mat3(transpose(inverse(model))) * in_normal;  
+            vec3 diffuse = max(dot(normalize(world_normal), light_dir), 0.0) * light_color;
+            // vertices without a normal (lines, particles) are not lit
+            bool lit = dot(in_normal, in_normal) > 0.0 && shading_params.x != 4.0;
+            vec3 lighting = lit ? ambient + diffuse : vec3(1.0);
+            vec3 shadow_lighting = lit ? ambient : vec3(1.0);
+            // depth cueing, darker with the distance
+            float cue = 1.0 - depth_cue * fog_amount(fog_distance);
+            base_color = vec4(cue * in_color.xyz, in_color.a);
+            polygon_color = vec4(lighting * base_color.xyz, in_color.a);
+            shadow_color = vec4(shadow_lighting * base_color.xyz, in_color.a);
+            vertex_color = polygon_color;
+            vertex_shadow_color = shadow_color;
+            light_space = light_view_proj * world_pos;
+            gl_Position = view_proj * world_pos;
+        }"#);
+
+    pub const FRAGMENT: &str = concat!(r#"#version 140
+        flat in lowp vec4 polygon_color;
+        flat in lowp vec4 shadow_color;
+        in vec4 vertex_color;
+        in vec4 vertex_shadow_color;
+        in vec4 base_color;
+        in vec3 world_normal;
+        in vec3 world_position;
+        in vec2 uv;
+        in float fog_distance;
+        in vec4 light_space;
+
+        uniform vec3 camera_position;
+        uniform vec3 light_direction;
+        uniform vec4 shading_params;
+        uniform sampler2D tex;
+        uniform sampler2D shadow_map;
+        uniform vec4 fog_color;
+        uniform vec4 emissive;
+        // render mode, depth range
+        uniform vec4 debug_params;
+        uniform vec4 debug_color;
+        // enabled, bias, texel size, soft
+        uniform vec4 shadow_params;
+
+        out vec4 color;
+"#, fog_amount!(), r#"
+        float unpack_depth(vec4 packed) {
+            return dot(packed, vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+        }
+
+        float light_sample(vec3 position, vec2 offset) {
+            float closest = unpack_depth(texture(shadow_map, position.xy + offset * shadow_params.z));
+            return position.z - shadow_params.y > closest ? 0.0 : 1.0;
+        }
+
+        float light_visibility() {
+            if (shadow_params.x == 0.0) {
+                return 1.0;
+            }
+            vec3 position = light_space.xyz / light_space.w * 0.5 + 0.5;
+            // outside of the shadow map
+            if (any(lessThan(position, vec3(0.0))) || any(greaterThan(position, vec3(1.0)))) {
+                return 1.0;
+            }
+            if (shadow_params.w == 0.0) {
+                return light_sample(position, vec2(0.0));
+            }
+            float sum = 0.0;
+            for (int x = -1; x <= 1; x++) {
+                for (int y = -1; y <= 1; y++) {
+                    sum += light_sample(position, vec2(x, y));
+                }
+            }
+            return sum / 9.0;
+        }
+
+        vec4 debug_view() {
+            vec3 normal = dot(world_normal, world_normal) > 0.0 ? normalize(world_normal) : vec3(0.0);
+            if (debug_params.x == 1.0) {
+                return vec4(normal * 0.5 + 0.5, 1.0);
+            } else if (debug_params.x == 2.0) {
+                return vec4(vec3(1.0 - clamp(fog_distance / debug_params.y, 0.0, 1.0)), 1.0);
+            } else if (debug_params.x == 3.0) {
+                // accumulated by additive blending, from red to white
+                return vec4(0.2, 0.08, 0.03, 1.0);
+            }
+            float diffuse = max(dot(normal, normalize(-light_direction)), 0.0);
+            return vec4(debug_color.rgb * (0.6 + 0.4 * diffuse), 1.0);
+        }
+
+        void main() {
+            if (debug_params.x != 0.0) {
+                if (texture(tex, uv).a < 0.01) {
+                    discard;
+                }
+                color = debug_view();
+                return;
+            }
+            vec4 lit_color = polygon_color;
+            vec4 dark_color = shadow_color;
+            if (shading_params.x == 1.0) {
+                lit_color = vertex_color;
+                dark_color = vertex_shadow_color;
+            } else if (shading_params.x == 2.0 || shading_params.x == 3.0) {
+                if (dot(world_normal, world_normal) > 0.0) {
+                    vec3 normal = normalize(world_normal);
+                    vec3 light_dir = normalize(-light_direction);
+                    float diffuse = max(dot(normal, light_dir), 0.0);
+                    float specular = 0.0;
+                    if (shading_params.x == 3.0 && diffuse > 0.0) {
+                        vec3 half_dir = normalize(light_dir + normalize(camera_position - world_position));
+                        specular = shading_params.y * pow(max(dot(normal, half_dir), 0.0), shading_params.z);
+                    }
+                    lit_color = vec4(base_color.rgb * (0.2 + diffuse) + specular, base_color.a);
+                    dark_color = vec4(base_color.rgb * 0.2, base_color.a);
+                } else {
+                    lit_color = base_color;
+                    dark_color = base_color;
+                }
+            }
+            color = mix(dark_color, lit_color, light_visibility()) * texture(tex, uv);
+            // fully transparent texels don't hide what is behind them
+            if (color.a < 0.01) {
+                discard;
+            }
+            color.rgb += emissive.rgb;
+            color.rgb = mix(color.rgb, fog_color.rgb, fog_amount(fog_distance));
+        }"#);
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["tex".to_string(), "shadow_map".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("model", UniformType::Mat4),
+                    UniformDesc::new("view_proj", UniformType::Mat4),
+                    UniformDesc::new("light_view_proj", UniformType::Mat4),
+                    UniformDesc::new("camera_position", UniformType::Float3),
+                    UniformDesc::new("fog_color", UniformType::Float4),
+                    UniformDesc::new("fog_params", UniformType::Float4),
+                    UniformDesc::new("depth_cue", UniformType::Float1),
+                    UniformDesc::new("light_direction", UniformType::Float3),
+                    UniformDesc::new("shadow_params", UniformType::Float4),
+                    UniformDesc::new("shading_params", UniformType::Float4),
+                    UniformDesc::new("emissive", UniformType::Float4),
+                    UniformDesc::new("debug_params", UniformType::Float4),
+                    UniformDesc::new("debug_color", UniformType::Float4),
+                ],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub model: Mat4,
+        pub view_proj: Mat4,
+        pub light_view_proj: Mat4,
+        pub camera_position: Vec3,
+        pub fog_color: [f32; 4],
+        pub fog_params: [f32; 4],
+        pub depth_cue: f32,
+        pub light_direction: Vec3,
+        pub shadow_params: [f32; 4],
+        pub shading_params: [f32; 4],
+        pub emissive: [f32; 4],
+        pub debug_params: [f32; 4],
+        pub debug_color: [f32; 4],
+    }
+}
+
+mod sky_shader {
+    use glam::Mat4;
+    use miniquad::*;
+
+    pub const VERTEX: &str = r#"#version 140
+        in vec2 in_pos;
+
+        out vec2 ndc;
+
+        void main() {
+            ndc = in_pos;
+            gl_Position = vec4(in_pos, 1.0, 1.0);
         }"#;
 
     pub const FRAGMENT: &str = r#"#version 140
-        flat in lowp vec4 polygon_color;
+        in vec2 ndc;
+
+        uniform mat4 inverse_view_proj;
+        // kind, cosine of the sun radius, star density, seed
+        uniform vec4 sky_params;
+        uniform vec4 zenith_color;
+        uniform vec4 horizon_color;
+        uniform vec4 ground_color;
+        uniform vec4 sun_direction;
+        uniform vec4 sun_color;
+        uniform samplerCube cubemap;
+
+        out vec4 color;
+
+        float hash(vec3 cell) {
+            return fract(sin(dot(cell, vec3(12.9898, 78.233, 37.719)) + sky_params.w) * 43758.5453);
+        }
+
+        vec3 gradient(vec3 direction) {
+            if (sun_color.a > 0.0 && dot(direction, sun_direction.xyz) > sky_params.y) {
+                return sun_color.rgb;
+            }
+            if (direction.y >= 0.0) {
+                return mix(horizon_color.rgb, zenith_color.rgb, sqrt(direction.y));
+            }
+            return mix(horizon_color.rgb, ground_color.rgb, min(-direction.y * 4.0, 1.0));
+        }
+
+        vec3 starfield(vec3 direction) {
+            vec3 projected = direction / max(max(abs(direction.x), abs(direction.y)), abs(direction.z));
+            vec3 cell = floor(projected * 128.0);
+            if (hash(cell) < sky_params.z) {
+                return mix(zenith_color.rgb, vec3(1.0), hash(cell + 0.5));
+            }
+            return zenith_color.rgb;
+        }
+
+        void main() {
+            vec4 near = inverse_view_proj * vec4(ndc, -1.0, 1.0);
+            vec4 far = inverse_view_proj * vec4(ndc, 1.0, 1.0);
+            vec3 direction = normalize(far.xyz / far.w - near.xyz / near.w);
+
+            if (sky_params.x == 0.0) {
+                color = vec4(gradient(direction), 1.0);
+            } else if (sky_params.x == 1.0) {
+                color = vec4(starfield(direction), 1.0);
+            } else {
+                color = vec4(texture(cubemap, direction).rgb, 1.0);
+            }
+        }"#;
+
+    pub fn meta() -> ShaderMeta {
+        ShaderMeta {
+            images: vec!["cubemap".to_string()],
+            uniforms: UniformBlockLayout {
+                uniforms: vec![
+                    UniformDesc::new("inverse_view_proj", UniformType::Mat4),
+                    UniformDesc::new("sky_params", UniformType::Float4),
+                    UniformDesc::new("zenith_color", UniformType::Float4),
+                    UniformDesc::new("horizon_color", UniformType::Float4),
+                    UniformDesc::new("ground_color", UniformType::Float4),
+                    UniformDesc::new("sun_direction", UniformType::Float4),
+                    UniformDesc::new("sun_color", UniformType::Float4),
+                ],
+            },
+        }
+    }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub inverse_view_proj: Mat4,
+        pub sky_params: [f32; 4],
+        pub zenith_color: [f32; 4],
+        pub horizon_color: [f32; 4],
+        pub ground_color: [f32; 4],
+        pub sun_direction: [f32; 4],
+        pub sun_color: [f32; 4],
+    }
+}
+
+mod shadow_shader {
+    use glam::Mat4;
+    use miniquad::*;
+
+    pub const VERTEX: &str = r#"#version 140
+        in vec3 in_pos;
+
+        uniform mat4 model;
+        uniform mat4 light_view_proj;
+
+        out float depth;
+
+        void main() {
+            gl_Position = light_view_proj * model * vec4(in_pos, 1.0);
+            depth = gl_Position.z / gl_Position.w * 0.5 + 0.5;
+        }"#;
+
+    pub const FRAGMENT: &str = r#"#version 140
+        in float depth;
+
         out vec4 color;
 
+        // spreads the depth over the 4 channels of an 8 bit texture
+        vec4 pack_depth(float depth) {
+            vec4 packed = fract(vec4(1.0, 255.0, 65025.0, 16581375.0) * depth);
+            return packed - packed.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+        }
+
         void main() {
-            color = polygon_color;
+            color = pack_depth(depth);
         }"#;
 
     pub fn meta() -> ShaderMeta {
@@ -449,7 +1454,7 @@ mod shader_3d {
             uniforms: UniformBlockLayout {
                 uniforms: vec![
                     UniformDesc::new("model", UniformType::Mat4),
-                    UniformDesc::new("view_proj", UniformType::Mat4),
+                    UniformDesc::new("light_view_proj", UniformType::Mat4),
                 ],
             },
         }
@@ -458,7 +1463,7 @@ mod shader_3d {
     #[repr(C)]
     pub struct Uniforms {
         pub model: Mat4,
-        pub view_proj: Mat4,
+        pub light_view_proj: Mat4,
     }
 }
 
@@ -469,28 +1474,39 @@ mod shader_2d {
     pub const VERTEX: &str = r#"#version 140
         in vec3 in_pos;
         in vec4 in_color;
+        in vec2 in_uv;
 
         uniform mat4 model;
         uniform mat4 view_proj;
 
         flat out lowp vec4 polygon_color;
+        out vec2 uv;
 
         void main() {
+            uv = in_uv;
             polygon_color = in_color;
             gl_Position = view_proj * vec4(in_pos, 1.0);
         }"#;
 
     pub const FRAGMENT: &str = r#"#version 140
         flat in lowp vec4 polygon_color;
+        in vec2 uv;
+
+        uniform sampler2D tex;
+
         out vec4 color;
 
         void main() {
-            color = polygon_color;
+            color = polygon_color * texture(tex, uv);
+            // fully transparent texels don't hide what is behind them
+            if (color.a < 0.01) {
+                discard;
+            }
         }"#;
 
     pub fn meta() -> ShaderMeta {
         ShaderMeta {
-            images: vec![],
+            images: vec!["tex".to_string()],
             uniforms: UniformBlockLayout {
                 uniforms: vec![
                     UniformDesc::new("model", UniformType::Mat4),
@@ -499,6 +1515,12 @@ mod shader_2d {
             },
         }
     }
+
+    #[repr(C)]
+    pub struct Uniforms {
+        pub model: Mat4,
+        pub view_proj: Mat4,
+    }
 }
 
 mod display_shader {
@@ -544,4 +1566,22 @@ mod display_shader {
     pub struct Uniforms {
         pub model: Mat4,
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_buffers_grow_with_their_index_type() {
+        let size = 4096 * std::mem::size_of::<i32>();
+        assert!(grown_buffer::<i32>(size, 4096).is_none());
+        match grown_buffer::<i32>(size, 6000) {
+            Some(BufferSource::Empty { size, element_size }) => {
+                assert_eq!(element_size, std::mem::size_of::<i32>());
+                assert_eq!(size, 8192 * std::mem::size_of::<i32>());
+            },
+            _ => panic!("the buffer should grow"),
+        }
+    }
+}
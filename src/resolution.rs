@@ -0,0 +1,79 @@
+use glam::{uvec2, UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// Size of the image drawn by the console and shape of its pixels on the display
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub size: UVec2,
+    /// Width over height of a pixel
+    pub pixel_aspect: f32,
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Resolution {
+    pub const DEFAULT: Resolution = Resolution::new(320, 200);
+    pub const HANDHELD: Resolution = Resolution::new(160, 144);
+    /// Slightly wide pixels, as on the home consoles of the 90s
+    pub const HOME_CONSOLE: Resolution = Resolution::new(256, 224).with_pixel_aspect(8.0 / 7.0);
+    pub const VGA: Resolution = Resolution::new(320, 240);
+    pub const WIDESCREEN: Resolution = Resolution::new(640, 360);
+
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: uvec2(width, height),
+            pixel_aspect: 1.0,
+        }
+    }
+
+    pub const fn with_pixel_aspect(mut self, pixel_aspect: f32) -> Self {
+        self.pixel_aspect = pixel_aspect;
+        self
+    }
+
+    pub fn as_vec2(&self) -> Vec2 {
+        self.size.as_vec2()
+    }
+
+    /// Width over height of the whole image on the display
+    pub fn aspect(&self) -> f32 {
+        self.size.x as f32 * self.pixel_aspect / self.size.y as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use crate::{color::Color, graphics::{Camera2d, Camera3d, Graphics}, renderer::{display_scale, RendererData}};
+
+    use super::*;
+
+    #[test]
+    fn cameras_and_display_follow_the_resolution() {
+        let resolution = Resolution::HOME_CONSOLE;
+        let centered = Camera2d::new();
+        let moved = Camera2d::new().with_position(vec2(10.0, 20.0));
+        assert_eq!(centered.position(&resolution), vec2(128.0, 112.0));
+        // wide pixels make a wider image
+        let aspect = 256.0 * 8.0 / 7.0 / 224.0;
+        assert!((Camera3d::new().aspect(&resolution) - aspect).abs() < 1.0e-5);
+        assert!(display_scale(vec2(800.0, 600.0), &resolution).abs_diff_eq(vec2(aspect * 0.75, 1.0), 1.0e-5));
+
+        let resolution = Resolution::WIDESCREEN;
+        assert!(display_scale(vec2(800.0, 600.0), &resolution).abs_diff_eq(vec2(1.0, 0.75), 1.0e-5));
+        // the default center follows the new resolution, a chosen position stays
+        assert_eq!(centered.position(&resolution), vec2(320.0, 180.0));
+        assert_eq!(moved.position(&resolution), vec2(10.0, 20.0));
+
+        let mut data = RendererData::new();
+        let mut graphics = Graphics { data: &mut data };
+        assert_eq!(graphics.resolution(), Resolution::DEFAULT);
+        graphics.set_resolution(resolution).pset(0, 0, Color::white());
+        assert_eq!(data.canvas.as_ref().unwrap().size(), resolution.size);
+    }
+}
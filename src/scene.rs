@@ -0,0 +1,365 @@
+use glam::Mat4;
+
+use crate::{graphics::Camera3d, light::Light, object::Object, transform::Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+pub enum NodeContent {
+    Empty,
+    Object(Object),
+    Camera(Camera3d),
+    Light(Light),
+}
+
+pub struct Node {
+    name: String,
+    transform: Transform,
+    world: Mat4,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    content: NodeContent,
+    dirty: bool,
+}
+
+impl Node {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// World transform as of the last call to `Scene::update`
+    pub fn world_transform(&self) -> &Mat4 {
+        &self.world
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    pub fn content(&self) -> &NodeContent {
+        &self.content
+    }
+}
+
+/// A hierarchy of nodes with local transforms.
+/// World transforms are cached and only recomputed for the nodes whose
+/// local transform (or one of their ancestors') changed since the last update.
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+    active_camera: Option<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_node(&mut self, name: &str, transform: Transform, content: NodeContent) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(
+            Node {
+                name: name.to_string(),
+                transform,
+                world: transform.matrix(),
+                parent: None,
+                children: Vec::new(),
+                content,
+                dirty: true,
+            }
+        );
+        self.roots.push(id);
+        id
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, name: &str, transform: Transform, content: NodeContent) -> NodeId {
+        let id = self.add_node(name, transform, content);
+        self.set_parent(id, Some(parent));
+        id
+    }
+
+    pub fn add_object(&mut self, name: &str, object: Object) -> NodeId {
+        let transform = Transform::from_matrix(object.transform());
+        self.add_node(name, transform, NodeContent::Object(object))
+    }
+
+    pub fn add_camera(&mut self, name: &str, camera: Camera3d) -> NodeId {
+        let transform = Transform::from_matrix(camera.transform());
+        let id = self.add_node(name, transform, NodeContent::Camera(camera));
+        if self.active_camera.is_none() {
+            self.active_camera = Some(id);
+        }
+        id
+    }
+
+    pub fn add_light(&mut self, name: &str, light: Light) -> NodeId {
+        let transform = Transform::new().with_translation(light.position());
+        self.add_node(name, transform, NodeContent::Light(light))
+    }
+
+    /// Attaches `id` to `parent`, or detaches it when `parent` is `None`.
+    /// The node keeps its local transform, so its world transform follows the new parent.
+    /// Returns false if the change would create a cycle.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(a) = ancestor {
+                if a == id {
+                    return false;
+                }
+                ancestor = self.nodes[a.0].parent;
+            }
+        }
+
+        match self.nodes[id.0].parent {
+            Some(old) => self.nodes[old.0].children.retain(|c| *c != id),
+            None => self.roots.retain(|r| *r != id),
+        }
+
+        match parent {
+            Some(p) => self.nodes[p.0].children.push(id),
+            None => self.roots.push(id),
+        }
+
+        self.nodes[id.0].parent = parent;
+        self.nodes[id.0].dirty = true;
+        true
+    }
+
+    pub fn node_id(&self, index: usize) -> Option<NodeId> {
+        (index < self.nodes.len()).then_some(NodeId(index))
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), node))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .map(NodeId)
+    }
+
+    pub fn transform(&self, id: NodeId) -> &Transform {
+        &self.nodes[id.0].transform
+    }
+
+    pub fn transform_mut(&mut self, id: NodeId) -> &mut Transform {
+        self.nodes[id.0].dirty = true;
+        &mut self.nodes[id.0].transform
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> &mut Self {
+        *self.transform_mut(id) = transform;
+        self
+    }
+
+    pub fn world_transform(&self, id: NodeId) -> &Mat4 {
+        &self.nodes[id.0].world
+    }
+
+    pub fn object(&self, id: NodeId) -> Option<&Object> {
+        match &self.nodes[id.0].content {
+            NodeContent::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn object_mut(&mut self, id: NodeId) -> Option<&mut Object> {
+        match &mut self.nodes[id.0].content {
+            NodeContent::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn camera(&self, id: NodeId) -> Option<&Camera3d> {
+        match &self.nodes[id.0].content {
+            NodeContent::Camera(camera) => Some(camera),
+            _ => None,
+        }
+    }
+
+    pub fn camera_mut(&mut self, id: NodeId) -> Option<&mut Camera3d> {
+        match &mut self.nodes[id.0].content {
+            NodeContent::Camera(camera) => Some(camera),
+            _ => None,
+        }
+    }
+
+    pub fn light(&self, id: NodeId) -> Option<&Light> {
+        match &self.nodes[id.0].content {
+            NodeContent::Light(light) => Some(light),
+            _ => None,
+        }
+    }
+
+    pub fn light_mut(&mut self, id: NodeId) -> Option<&mut Light> {
+        match &mut self.nodes[id.0].content {
+            NodeContent::Light(light) => Some(light),
+            _ => None,
+        }
+    }
+
+    pub fn set_active_camera(&mut self, id: NodeId) -> &mut Self {
+        self.active_camera = Some(id);
+        self
+    }
+
+    pub fn active_camera_id(&self) -> Option<NodeId> {
+        self.active_camera
+    }
+
+    pub fn active_camera(&self) -> Option<&Camera3d> {
+        self.active_camera.and_then(|id| self.camera(id))
+    }
+
+    /// Recomputes the world transforms of the nodes that changed
+    /// and pushes them to the attached objects, cameras and lights
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Mat4, bool)> = self.roots
+            .iter()
+            .rev()
+            .map(|id| (*id, Mat4::IDENTITY, false))
+            .collect();
+
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.transform.matrix();
+                node.dirty = false;
+
+                let world = node.world;
+                match &mut node.content {
+                    NodeContent::Empty => {},
+                    NodeContent::Object(object) => {
+                        object.set_transform(&world);
+                    },
+                    NodeContent::Camera(camera) => {
+                        camera.set_transform(&world);
+                    },
+                    NodeContent::Light(light) => {
+                        light.set_position(world.w_axis.truncate());
+                    },
+                }
+            }
+
+            let world = node.world;
+            for child in node.children.iter().rev() {
+                stack.push((*child, world, changed));
+            }
+        }
+    }
+
+    /// Visits the nodes depth first, parents before their children
+    pub fn walk(&self, mut f: impl FnMut(NodeId, &Node)) {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id.0];
+            f(id, node);
+            stack.extend(node.children.iter().rev());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Quat};
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn child_follows_parent() {
+        let mut scene = Scene::new();
+        let tank = scene.add_node(
+            "tank",
+            Transform::new().with_translation(vec3(10.0, 0.0, 0.0)),
+            NodeContent::Empty
+        );
+        let turret = scene.add_child(
+            tank,
+            "turret",
+            Transform::new().with_translation(vec3(0.0, 1.0, 0.0)),
+            NodeContent::Empty
+        );
+        scene.update();
+        assert!(scene.world_transform(turret).w_axis.truncate().abs_diff_eq(vec3(10.0, 1.0, 0.0), 1.0e-5));
+
+        scene.transform_mut(tank).rotate_z(PI / 2.0);
+        scene.update();
+        assert!(scene.world_transform(turret).w_axis.truncate().abs_diff_eq(vec3(9.0, 0.0, 0.0), 1.0e-5));
+    }
+
+    #[test]
+    fn world_transform_is_cached_until_update() {
+        let mut scene = Scene::new();
+        let node = scene.add_node("node", Transform::new(), NodeContent::Empty);
+        scene.update();
+        scene.set_transform(node, Transform::new().with_rotation(Quat::from_rotation_y(1.0)));
+        assert_eq!(*scene.world_transform(node), Mat4::IDENTITY);
+        scene.update();
+        assert!(scene.world_transform(node).abs_diff_eq(Mat4::from_rotation_y(1.0), 1.0e-5));
+    }
+
+    #[test]
+    fn attached_object_gets_world_transform() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node(
+            "parent",
+            Transform::new().with_scale(vec3(2.0, 2.0, 2.0)),
+            NodeContent::Empty
+        );
+        let cube = scene.add_object("cube", Object::new_cube(crate::color::Color::red()));
+        scene.set_parent(cube, Some(parent));
+        scene.transform_mut(cube).translate(vec3(1.0, 0.0, 0.0));
+        scene.update();
+        let object = scene.object(cube).unwrap();
+        assert!(object.transform().w_axis.truncate().abs_diff_eq(vec3(2.0, 0.0, 0.0), 1.0e-5));
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut scene = Scene::new();
+        let a = scene.add_node("a", Transform::new(), NodeContent::Empty);
+        let b = scene.add_child(a, "b", Transform::new(), NodeContent::Empty);
+        assert!(!scene.set_parent(a, Some(b)));
+        assert_eq!(scene.node(a).parent(), None);
+        assert_eq!(scene.roots(), &[a]);
+    }
+}
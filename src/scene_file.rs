@@ -0,0 +1,407 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{color::Color, graphics::{Camera, Camera3d, Fog, Projection, Rect2d, Vertex}, light::{Light, Shading, Shadow}, material::{BlendMode, CullMode, Material}, object::Object, scene::{NodeContent, Scene}, shapes::{Cube, Plane}, transform::Transform};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Serialize(String),
+    UnknownMesh {
+        node: String,
+        mesh: String,
+    },
+    UnnamedMesh {
+        node: String,
+    },
+    InvalidParent {
+        node: String,
+        parent: usize,
+    },
+    InvalidCamera {
+        camera: usize,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "io error: {}", err),
+            SceneError::Parse { line, column, message } => write!(f, "{}:{}: {}", line, column, message),
+            SceneError::Serialize(message) => write!(f, "cannot serialize scene: {}", message),
+            SceneError::UnknownMesh { node, mesh } => write!(f, "node '{}' references unknown mesh '{}'", node, mesh),
+            SceneError::UnnamedMesh { node } => write!(f, "node '{}' holds an object without a mesh name", node),
+            SceneError::InvalidParent { node, parent } => write!(f, "node '{}' has invalid parent {}", node, parent),
+            SceneError::InvalidCamera { camera } => write!(f, "active camera {} is not a camera node", camera),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        SceneError::Parse {
+            line: err.position.line,
+            column: err.position.col,
+            message: err.code.to_string(),
+        }
+    }
+}
+
+struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<i32>,
+}
+
+/// The meshes a scene file can reference by name.
+/// Built-in shapes are always available.
+pub struct MeshLibrary {
+    meshes: HashMap<String, Mesh>,
+}
+
+impl Default for MeshLibrary {
+    fn default() -> Self {
+        let mut library = Self {
+            meshes: HashMap::new(),
+        };
+        library.register(Cube::NAME, Cube::vertices(Color::white()), Cube::indices());
+        library.register(Plane::NAME, Plane::vertices(Color::white()), Plane::indices());
+        library
+    }
+}
+
+impl MeshLibrary {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register(&mut self, name: &str, vertices: Vec<Vertex>, indices: Vec<i32>) -> &mut Self {
+        self.meshes.insert(name.to_string(), Mesh { vertices, indices });
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.meshes.contains_key(name)
+    }
+
+    pub fn instantiate(&self, name: &str, color: Color) -> Option<Object> {
+        self.meshes.get(name).map(|mesh| {
+            Object::new_mesh(mesh.vertices.clone(), mesh.indices.clone())
+                .with_mesh_name(name)
+                .with_color(color)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SceneDesc {
+    #[serde(default)]
+    active_camera: Option<usize>,
+    nodes: Vec<NodeDesc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeDesc {
+    name: String,
+    #[serde(default)]
+    parent: Option<usize>,
+    #[serde(default)]
+    transform: Transform,
+    #[serde(default)]
+    content: ContentDesc,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+enum ContentDesc {
+    #[default]
+    Empty,
+    Object {
+        mesh: String,
+        #[serde(default = "Color::white")]
+        color: Color,
+        #[serde(default)]
+        shading: Shading,
+        #[serde(default = "no_emission")]
+        emissive: Color,
+        #[serde(default)]
+        blend: BlendMode,
+        #[serde(default)]
+        cull: CullMode,
+        #[serde(default)]
+        wireframe: bool,
+    },
+    Camera {
+        #[serde(default = "full_viewport")]
+        viewport: Rect2d,
+        #[serde(default)]
+        background: Color,
+        #[serde(default)]
+        projection: Projection,
+        #[serde(default = "default_near")]
+        near: f32,
+        #[serde(default = "default_far")]
+        far: f32,
+        #[serde(default)]
+        fog: Option<Fog>,
+    },
+    Light {
+        #[serde(default = "default_light_direction")]
+        direction: Vec3,
+        #[serde(default)]
+        shadow: Option<Shadow>,
+    },
+}
+
+fn no_emission() -> Color {
+    Material::default().emissive
+}
+
+fn default_light_direction() -> Vec3 {
+    Light::new().direction()
+}
+
+fn full_viewport() -> Rect2d {
+    *Camera3d::new().viewport()
+}
+
+fn default_near() -> f32 {
+    Camera3d::DEFAULT_NEAR
+}
+
+fn default_far() -> f32 {
+    Camera3d::DEFAULT_FAR
+}
+
+impl Scene {
+    /// Serializes the scene to RON, the textures and debug render modes of the materials are not saved
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        let mut nodes = Vec::with_capacity(self.len());
+        for (_, node) in self.nodes() {
+            let content = match node.content() {
+                NodeContent::Empty => ContentDesc::Empty,
+                NodeContent::Object(object) => {
+                    let material = object.material();
+                    ContentDesc::Object {
+                        mesh: object.mesh_name()
+                            .ok_or_else(|| SceneError::UnnamedMesh { node: node.name().to_string() })?
+                            .to_string(),
+                        color: material.color,
+                        shading: material.shading,
+                        emissive: material.emissive,
+                        blend: material.blend,
+                        cull: material.cull,
+                        wireframe: material.wireframe,
+                    }
+                },
+                NodeContent::Camera(camera) => ContentDesc::Camera {
+                    viewport: *camera.viewport(),
+                    background: camera.background(),
+                    projection: camera.projection(),
+                    near: camera.near(),
+                    far: camera.far(),
+                    fog: camera.fog(),
+                },
+                NodeContent::Light(light) => ContentDesc::Light {
+                    direction: light.direction(),
+                    shadow: light.shadow().copied(),
+                },
+            };
+
+            nodes.push(
+                NodeDesc {
+                    name: node.name().to_string(),
+                    parent: node.parent().map(|p| p.index()),
+                    transform: *node.transform(),
+                    content,
+                }
+            );
+        }
+
+        let desc = SceneDesc {
+            active_camera: self.active_camera_id().map(|id| id.index()),
+            nodes,
+        };
+
+        ron::ser::to_string_pretty(&desc, ron::ser::PrettyConfig::default())
+            .map_err(|err| SceneError::Serialize(err.to_string()))
+    }
+
+    /// Builds a scene from RON, instantiating the objects from `meshes`
+    pub fn from_ron(source: &str, meshes: &MeshLibrary) -> Result<Scene, SceneError> {
+        let desc: SceneDesc = ron::from_str(source)?;
+
+        let mut scene = Scene::new();
+        for node in desc.nodes.iter() {
+            let content = match &node.content {
+                ContentDesc::Empty => NodeContent::Empty,
+                ContentDesc::Object { mesh, color, shading, emissive, blend, cull, wireframe } => NodeContent::Object(
+                    meshes.instantiate(mesh, *color)
+                        .ok_or_else(|| SceneError::UnknownMesh {
+                            node: node.name.clone(),
+                            mesh: mesh.clone(),
+                        })?
+                        .with_material(
+                            Material::new()
+                                .with_color(*color)
+                                .with_shading(*shading)
+                                .with_emissive(*emissive)
+                                .with_blend(*blend)
+                                .with_cull(*cull)
+                                .with_wireframe(*wireframe)
+                        )
+                ),
+                ContentDesc::Camera { viewport, background, projection, near, far, fog } => {
+                    let mut camera = Camera3d::new()
+                        .with_viewport(viewport)
+                        .with_background(*background)
+                        .with_projection(*projection)
+                        .with_clip_planes(*near, *far);
+                    camera.set_fog(*fog);
+                    NodeContent::Camera(camera)
+                },
+                ContentDesc::Light { direction, shadow } => {
+                    let mut light = Light::new().with_direction(*direction);
+                    light.set_shadow(*shadow);
+                    NodeContent::Light(light)
+                },
+            };
+            scene.add_node(&node.name, node.transform, content);
+        }
+
+        for (index, node) in desc.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                let invalid = || SceneError::InvalidParent {
+                    node: node.name.clone(),
+                    parent,
+                };
+                let parent_id = scene.node_id(parent).ok_or_else(invalid)?;
+                let id = scene.node_id(index).unwrap();
+                if !scene.set_parent(id, Some(parent_id)) {
+                    return Err(invalid());
+                }
+            }
+        }
+
+        if let Some(camera) = desc.active_camera {
+            match scene.node_id(camera) {
+                Some(id) if scene.camera(id).is_some() => {
+                    scene.set_active_camera(id);
+                },
+                _ => return Err(SceneError::InvalidCamera { camera }),
+            }
+        }
+
+        scene.update();
+        Ok(scene)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>, meshes: &MeshLibrary) -> Result<Scene, SceneError> {
+        Scene::from_ron(&fs::read_to_string(path)?, meshes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Quat};
+
+    use super::*;
+
+    fn sample_scene() -> Scene {
+        let mut scene = Scene::new();
+        let camera = scene.add_camera(
+            "camera",
+            Camera3d::new()
+                .with_background(Color::gray())
+                .with_orthographic(12.0)
+                .with_clip_planes(0.5, 40.0)
+                .with_fog(Fog::linear(10.0, 40.0).with_depth_cue(0.5))
+        );
+        scene.transform_mut(camera).translate(vec3(0.0, 2.0, 8.0));
+        let tank = scene.add_object(
+            "tank",
+            Object::new_cube(Color::green()).with_material(
+                Material::new()
+                    .with_color(Color::green())
+                    .with_shading(Shading::Gouraud)
+                    .with_emissive(Color::new(0.2, 0.0, 0.0, 0.0))
+                    .with_cull(CullMode::Back)
+            )
+        );
+        scene.set_transform(
+            tank,
+            Transform::new()
+                .with_translation(vec3(1.5, 0.0, -2.0))
+                .with_rotation(Quat::from_rotation_y(0.3))
+                .with_scale(vec3(2.0, 1.0, 3.0))
+        );
+        let turret = scene.add_object("turret", Object::new_cube(Color::new(0.1, 0.2, 0.3, 1.0)));
+        scene.set_parent(turret, Some(tank));
+        scene.add_light(
+            "sun",
+            Light::new()
+                .with_direction(vec3(1.0, -2.0, 0.5))
+                .with_shadow(Shadow::new().with_resolution(256).with_soft(true))
+        );
+        scene.update();
+        scene
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let meshes = MeshLibrary::new();
+        let ron = sample_scene().to_ron().unwrap();
+        let loaded = Scene::from_ron(&ron, &meshes).unwrap();
+        assert_eq!(loaded.to_ron().unwrap(), ron);
+        assert!(loaded.active_camera().unwrap().fog().is_some());
+
+        let tank = loaded.find("tank").unwrap();
+        assert_eq!(loaded.object(tank).unwrap().color(), Color::green());
+        assert_eq!(loaded.object(tank).unwrap().shading(), Shading::Gouraud);
+        assert_eq!(loaded.object(tank).unwrap().material().cull, CullMode::Back);
+        assert_eq!(loaded.node(loaded.find("turret").unwrap()).parent(), Some(tank));
+
+        let sun = loaded.light(loaded.find("sun").unwrap()).unwrap();
+        assert!(sun.direction().abs_diff_eq(vec3(1.0, -2.0, 0.5).normalize(), 1.0e-5));
+        assert_eq!(sun.shadow(), Some(&Shadow::new().with_resolution(256).with_soft(true)));
+    }
+
+    #[test]
+    fn unknown_mesh_is_reported() {
+        let source = r#"(nodes: [(name: "rock", content: Object(mesh: "boulder"))])"#;
+        match Scene::from_ron(source, &MeshLibrary::new()) {
+            Err(SceneError::UnknownMesh { node, mesh }) => {
+                assert_eq!(node, "rock");
+                assert_eq!(mesh, "boulder");
+            },
+            _ => panic!("expected an unknown mesh error"),
+        }
+    }
+
+    #[test]
+    fn parse_errors_have_a_position() {
+        let source = "(\n    nodes: [\n        (name: \"a\", parent: oops),\n    ],\n)";
+        match Scene::from_ron(source, &MeshLibrary::new()) {
+            Err(SceneError::Parse { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected a parse error"),
+        }
+    }
+}
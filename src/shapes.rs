@@ -3,6 +3,8 @@ use crate::{color::Color, graphics::Vertex};
 pub struct Cube {}
 
 impl Cube {
+    pub const NAME: &'static str = "cube";
+
     pub fn vertices(color: Color) -> Vec<Vertex> {
         let p0 = [-0.5, -0.5, -0.5];
         let p1 = [0.5, -0.5, -0.5];
@@ -20,186 +22,222 @@ impl Cube {
                 position: p0,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p2,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p1,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p2,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p3,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p1,
                 color: color.as_array(),
                 normal: [0.0, 0.0, -1.0],
+                uv: [0.0, 1.0],
             },
             // face 2 +z
             Vertex {
                 position: p4,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p5,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p6,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p5,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p7,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p6,
                 color: color.as_array(),
                 normal: [0.0, 0.0, 1.0],
+                uv: [0.0, 0.0],
             },
             // face 3 -x
             Vertex {
                 position: p0,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p6,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p2,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p4,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p6,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p0,
                 color: color.as_array(),
                 normal: [-1.0, 0.0, 0.0],
+                uv: [0.0, 1.0],
             },
             // face 4 +x
             Vertex {
                 position: p1,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p7,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p5,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p1,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p3,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p7,
                 color: color.as_array(),
                 normal: [1.0, 0.0, 0.0],
+                uv: [0.0, 0.0],
             },
             // face 4 -y
             Vertex {
                 position: p0,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p1,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p5,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p0,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: p5,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p4,
                 color: color.as_array(),
                 normal: [0.0, -1.0, 0.0],
+                uv: [0.0, 0.0],
             },
             // face 4 +y
             Vertex {
                 position: p3,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: p2,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p7,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p7,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [1.0, 1.0],
             },
             Vertex {
                 position: p2,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: p6,
                 color: color.as_array(),
                 normal: [0.0, 1.0, 0.0],
+                uv: [0.0, 1.0],
             },
         ]
     }
@@ -212,6 +250,8 @@ impl Cube {
 pub struct Plane {}
 
 impl Plane {
+    pub const NAME: &'static str = "plane";
+
     pub fn vertices(color: Color) -> Vec<Vertex> {
         let normal = [0.0, 1.0, 0.0];
         let color = color.as_array();
@@ -221,21 +261,25 @@ impl Plane {
                 position: [-0.5, 0.0, -0.5],
                 color,
                 normal,
+                uv: [0.0, 0.0],
             },
             Vertex {
                 position: [0.5, 0.0, -0.5],
                 color,
                 normal,
+                uv: [1.0, 0.0],
             },
             Vertex {
                 position: [-0.5, 0.0, 0.5],
                 color,
                 normal,
+                uv: [0.0, 1.0],
             },
             Vertex {
                 position: [0.5, 0.0, 0.5],
                 color,
                 normal,
+                uv: [1.0, 1.0],
             },
         ]
     }
@@ -0,0 +1,301 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Mat4, Vec2};
+
+use crate::{color::Color, graphics::{Graphics, Vertex}, material::{BlendMode, Material}, renderer::{Mode, Primitive}, texture::Texture};
+
+/// Sides of a curve of the given radius, about one every 4 pixels of the default 2D camera
+fn segments(radius: f32, angle: f32) -> usize {
+    ((radius * angle.abs() / 4.0).ceil() as usize).clamp(4, 64)
+}
+
+/// Points of an elliptic arc, angles are in radians from +x toward +y, both ends included
+pub fn arc_points(center: Vec2, radii: Vec2, start_angle: f32, end_angle: f32) -> Vec<Vec2> {
+    let count = segments(radii.max_element(), end_angle - start_angle);
+    (0..=count)
+        .map(|i| {
+            let angle = start_angle + (end_angle - start_angle) * i as f32 / count as f32;
+            center + radii * vec2(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+pub fn ellipse_points(center: Vec2, radii: Vec2) -> Vec<Vec2> {
+    let mut points = arc_points(center, radii, 0.0, TAU);
+    // the last point closes the loop
+    points.pop();
+    points
+}
+
+pub fn rounded_rectangle_points(position: Vec2, size: Vec2, radius: f32) -> Vec<Vec2> {
+    let radius = radius.clamp(0.0, size.min_element() * 0.5);
+    let radii = Vec2::splat(radius);
+    let min = position + radii;
+    let max = position + size - radii;
+    let quarter = TAU / 4.0;
+    [
+        (vec2(max.x, min.y), -quarter),
+        (max, 0.0),
+        (vec2(min.x, max.y), quarter),
+        (min, 2.0 * quarter),
+    ]
+        .into_iter()
+        .flat_map(|(center, start)| arc_points(center, radii, start, start + quarter))
+        .collect()
+}
+
+/// Twice the signed area, positive when the points turn from +x toward +y
+fn signed_area(points: &[Vec2]) -> f32 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum()
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    (d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0) || (d1 <= 0.0 && d2 <= 0.0 && d3 <= 0.0)
+}
+
+/// Triangles of a simple polygon, convex or concave, by ear clipping
+pub fn triangulate(points: &[Vec2]) -> Vec<i32> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let winding = signed_area(points).signum();
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut indices = Vec::with_capacity((points.len() - 2) * 3);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = [(i + count - 1) % count, i, (i + 1) % count].map(|j| points[remaining[j]]);
+            // convex corner without any other point inside
+            (b - a).perp_dot(c - b) * winding > 0.0 && remaining
+                .iter()
+                .map(|&j| points[j])
+                .filter(|p| *p != a && *p != b && *p != c)
+                .all(|p| !in_triangle(p, a, b, c))
+        });
+        let Some(i) = ear else {
+            // self-intersecting or degenerate, the rest is drawn as a fan
+            break;
+        };
+        indices.extend([remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]].map(|j| j as i32));
+        remaining.remove(i);
+    }
+
+    for i in 1..remaining.len() - 1 {
+        indices.extend([remaining[0], remaining[i], remaining[i + 1]].map(|j| j as i32));
+    }
+    indices
+}
+
+/// Quads of a line of the given thickness through the points, with mitered corners
+pub fn stroke(points: &[Vec2], thickness: f32, closed: bool) -> (Vec<Vec2>, Vec<i32>) {
+    let count = points.len();
+    if count < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    let half = thickness * 0.5;
+    let normal = |a: Vec2, b: Vec2| (b - a).normalize_or_zero().perp();
+
+    let mut vertices = Vec::with_capacity(count * 2);
+    for i in 0..count {
+        let previous = if i > 0 { Some(points[i - 1]) } else if closed { Some(points[count - 1]) } else { None };
+        let next = if i + 1 < count { Some(points[i + 1]) } else if closed { Some(points[0]) } else { None };
+        let offset = match (previous, next) {
+            (Some(previous), Some(next)) => {
+                let (n0, n1) = (normal(previous, points[i]), normal(points[i], next));
+                let miter = (n0 + n1).normalize_or_zero();
+                // sharp corners are cut to avoid long spikes
+                miter * half / miter.dot(n1).max(0.25)
+            },
+            (Some(previous), None) => normal(previous, points[i]) * half,
+            (None, Some(next)) => normal(points[i], next) * half,
+            (None, None) => Vec2::ZERO,
+        };
+        vertices.push(points[i] + offset);
+        vertices.push(points[i] - offset);
+    }
+
+    let quads = if closed { count } else { count - 1 };
+    let indices = (0..quads)
+        .flat_map(|i| {
+            let (a, b) = ((i * 2) as i32, (((i + 1) % count) * 2) as i32);
+            [a, a + 1, b, a + 1, b + 1, b]
+        })
+        .collect();
+    (vertices, indices)
+}
+
+/// Filled and outlined shapes in the XY plane, sized in pixels with the default 2D camera
+impl Graphics<'_> {
+    pub fn draw_triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Color) -> &mut Self {
+        self.draw_shape(&[a, b, c], &[0, 1, 2], color)
+    }
+
+    pub fn draw_triangle_outline(&mut self, a: Vec2, b: Vec2, c: Vec2, thickness: f32, color: Color) -> &mut Self {
+        self.draw_polygon_outline(&[a, b, c], thickness, color)
+    }
+
+    pub fn draw_rectangle_outline(&mut self, position: Vec2, size: Vec2, thickness: f32, color: Color) -> &mut Self {
+        let points = [position, position + vec2(size.x, 0.0), position + size, position + vec2(0.0, size.y)];
+        self.draw_polygon_outline(&points, thickness, color)
+    }
+
+    pub fn draw_rounded_rectangle(&mut self, position: Vec2, size: Vec2, radius: f32, color: Color) -> &mut Self {
+        self.draw_convex(&rounded_rectangle_points(position, size, radius), color)
+    }
+
+    pub fn draw_rounded_rectangle_outline(&mut self, position: Vec2, size: Vec2, radius: f32, thickness: f32, color: Color) -> &mut Self {
+        self.draw_polygon_outline(&rounded_rectangle_points(position, size, radius), thickness, color)
+    }
+
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, color: Color) -> &mut Self {
+        self.draw_ellipse(center, Vec2::splat(radius), color)
+    }
+
+    pub fn draw_circle_outline(&mut self, center: Vec2, radius: f32, thickness: f32, color: Color) -> &mut Self {
+        self.draw_ellipse_outline(center, Vec2::splat(radius), thickness, color)
+    }
+
+    pub fn draw_ellipse(&mut self, center: Vec2, radii: Vec2, color: Color) -> &mut Self {
+        self.draw_convex(&ellipse_points(center, radii), color)
+    }
+
+    pub fn draw_ellipse_outline(&mut self, center: Vec2, radii: Vec2, thickness: f32, color: Color) -> &mut Self {
+        self.draw_polygon_outline(&ellipse_points(center, radii), thickness, color)
+    }
+
+    /// Part of a circle outline, angles are in radians from +x toward +y
+    pub fn draw_arc(&mut self, center: Vec2, radius: f32, start_angle: f32, end_angle: f32, thickness: f32, color: Color) -> &mut Self {
+        let (vertices, indices) = stroke(&arc_points(center, Vec2::splat(radius), start_angle, end_angle), thickness, false);
+        self.draw_shape(&vertices, &indices, color)
+    }
+
+    /// Any simple polygon, its points in either winding order
+    pub fn draw_polygon(&mut self, points: &[Vec2], color: Color) -> &mut Self {
+        self.draw_shape(points, &triangulate(points), color)
+    }
+
+    pub fn draw_polygon_outline(&mut self, points: &[Vec2], thickness: f32, color: Color) -> &mut Self {
+        let (vertices, indices) = stroke(points, thickness, true);
+        self.draw_shape(&vertices, &indices, color)
+    }
+
+    /// Sets a pixel of the canvas drawn over the image at the end of the frame.
+    /// The canvas is kept from one frame to the next, see `clear_canvas`.
+    pub fn pset(&mut self, x: i32, y: i32, color: Color) -> &mut Self {
+        let size = self.data.resolution.size;
+        // cleared when the resolution changes
+        if self.data.canvas.as_ref().is_some_and(|canvas| canvas.size() != size) {
+            self.data.canvas = None;
+        }
+        if x >= 0 && y >= 0 {
+            self.data.canvas
+                .get_or_insert_with(|| Texture::from_color(size.x, size.y, Color::new(0.0, 0.0, 0.0, 0.0)))
+                .set_pixel(x as u32, y as u32, color);
+        }
+        self
+    }
+
+    /// Pixel of the canvas set with `pset`, transparent if it was never set
+    pub fn pget(&self, x: i32, y: i32) -> Color {
+        self.data.canvas
+            .as_ref()
+            .filter(|_| x >= 0 && y >= 0)
+            .and_then(|canvas| canvas.pixel(x as u32, y as u32))
+            .unwrap_or(Color::new(0.0, 0.0, 0.0, 0.0))
+    }
+
+    pub fn clear_canvas(&mut self) -> &mut Self {
+        self.data.canvas = None;
+        self
+    }
+
+    /// Draws the canvas over the whole image, done at the end of the frame
+    pub fn flush_canvas(&mut self) -> &mut Self {
+        let Some(canvas) = self.data.canvas.clone() else {
+            return self;
+        };
+        let size = self.data.resolution.as_vec2();
+        self.data.view_proj = Mat4::orthographic_rh_gl(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+        self.data.camera_transform = Mat4::IDENTITY;
+        self.data.mode = Mode::Mode2d;
+        self.data.target = None;
+        self.data.viewport.position = Vec2::ZERO;
+        self.data.viewport.size = Vec2::ONE;
+        self.data.fog = None;
+
+        // in front of everything drawn before
+        let corner = |x: f32, y: f32| Vertex {
+            position: [x * size.x, y * size.y, 0.999],
+            color: [1.0; 4],
+            normal: [0.0; 3],
+            uv: [x, y],
+        };
+        let vertices = vec![corner(0.0, 0.0), corner(1.0, 0.0), corner(0.0, 1.0), corner(1.0, 1.0)];
+        let material = Material::unlit().with_blend(BlendMode::Alpha).with_texture(canvas);
+        self.new_draw_call(&vertices, &vec![0, 1, 2, 1, 3, 2], &Mat4::IDENTITY, Primitive::Triangles, &material)
+    }
+
+    fn draw_convex(&mut self, points: &[Vec2], color: Color) -> &mut Self {
+        let indices: Vec<i32> = (1..points.len().saturating_sub(1) as i32)
+            .flat_map(|i| [0, i, i + 1])
+            .collect();
+        self.draw_shape(points, &indices, color)
+    }
+
+    fn draw_shape(&mut self, points: &[Vec2], indices: &[i32], color: Color) -> &mut Self {
+        let vertices = points
+            .iter()
+            .map(|point| Vertex {
+                position: [point.x, point.y, 0.0],
+                color: color.as_array(),
+                normal: [0.0, 0.0, 0.0],
+                uv: [0.0, 0.0],
+            })
+            .collect();
+        self.new_draw_call(&vertices, &indices.to_vec(), &Mat4::IDENTITY, Primitive::Triangles, &Material::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[Vec2], indices: &[i32]) -> f32 {
+        indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| points[i as usize]);
+                (b - a).perp_dot(c - a).abs() * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn concave_polygons_are_triangulated() {
+        // an L shape, in both windings
+        let mut l_shape = vec![vec2(0.0, 0.0), vec2(2.0, 0.0), vec2(2.0, 1.0), vec2(1.0, 1.0), vec2(1.0, 2.0), vec2(0.0, 2.0)];
+        for _ in 0..2 {
+            let indices = triangulate(&l_shape);
+            assert_eq!(indices.len(), 4 * 3);
+            assert!((area(&l_shape, &indices) - 3.0).abs() < 1.0e-5);
+            l_shape.reverse();
+        }
+    }
+
+    #[test]
+    fn outlines_have_the_requested_thickness() {
+        let square = [vec2(0.0, 0.0), vec2(4.0, 0.0), vec2(4.0, 4.0), vec2(0.0, 4.0)];
+        let (vertices, indices) = stroke(&square, 2.0, true);
+        assert_eq!(indices.len(), 4 * 6);
+        // 6x6 outer square minus the 2x2 hole
+        assert!((area(&vertices, &indices) - 32.0).abs() < 1.0e-4);
+    }
+}
@@ -0,0 +1,568 @@
+use glam::{Mat3, Mat4, Quat, Vec3};
+
+use crate::{animation::{Interpolate, LoopMode, Track}, graphics::Vertex, object::Object, time::TimeStep, transform::Transform};
+
+pub const MAX_JOINT_INFLUENCES: usize = 4;
+
+pub struct Joint {
+    name: String,
+    parent: Option<usize>,
+    rest: Transform,
+    inverse_bind: Mat4,
+}
+
+impl Joint {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn rest(&self) -> &Transform {
+        &self.rest
+    }
+
+    pub fn inverse_bind(&self) -> &Mat4 {
+        &self.inverse_bind
+    }
+}
+
+/// A joint hierarchy. Joints are stored parents first.
+#[derive(Default)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a joint whose bind pose is its rest pose
+    pub fn add_joint(&mut self, name: &str, parent: Option<usize>, rest: Transform) -> usize {
+        let parent_world = parent.map_or(Mat4::IDENTITY, |p| self.joints[p].inverse_bind.inverse());
+        let inverse_bind = (parent_world * rest.matrix()).inverse();
+        self.add_joint_with_inverse_bind(name, parent, rest, inverse_bind)
+    }
+
+    pub fn add_joint_with_inverse_bind(&mut self, name: &str, parent: Option<usize>, rest: Transform, inverse_bind: Mat4) -> usize {
+        assert!(parent.is_none_or(|p| p < self.joints.len()), "the parent joint must be added first");
+
+        self.joints.push(
+            Joint {
+                name: name.to_string(),
+                parent,
+                rest,
+                inverse_bind,
+            }
+        );
+        self.joints.len() - 1
+    }
+
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            locals: self.joints.iter().map(|joint| joint.rest).collect(),
+        }
+    }
+
+    pub fn world_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        let mut worlds: Vec<Mat4> = Vec::with_capacity(self.joints.len());
+        for (joint, local) in self.joints.iter().zip(pose.locals.iter()) {
+            let world = match joint.parent {
+                Some(parent) => worlds[parent] * local.matrix(),
+                None => local.matrix(),
+            };
+            worlds.push(world);
+        }
+        worlds
+    }
+
+    /// Matrices moving a vertex from the bind pose to `pose`
+    pub fn skinning_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        self.world_matrices(pose)
+            .iter()
+            .zip(self.joints.iter())
+            .map(|(world, joint)| *world * joint.inverse_bind)
+            .collect()
+    }
+}
+
+/// Local transforms of every joint of a skeleton
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    locals: Vec<Transform>,
+}
+
+impl Pose {
+    pub fn locals(&self) -> &[Transform] {
+        &self.locals
+    }
+
+    pub fn local_mut(&mut self, joint: usize) -> &mut Transform {
+        &mut self.locals[joint]
+    }
+
+    /// Interpolates every joint towards `other`, `weight` being the share of `other`
+    pub fn blend(&self, other: &Pose, weight: f32) -> Pose {
+        Pose {
+            locals: self.locals
+                .iter()
+                .zip(other.locals.iter())
+                .map(|(a, b)| Transform {
+                    translation: a.translation.interpolate(&b.translation, weight),
+                    rotation: a.rotation.interpolate(&b.rotation, weight),
+                    scale: a.scale.interpolate(&b.scale, weight),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JointChannel {
+    pub joint: usize,
+    pub position: Option<Track<Vec3>>,
+    pub rotation: Option<Track<Quat>>,
+    pub scale: Option<Track<Vec3>>,
+}
+
+impl JointChannel {
+    pub fn new(joint: usize) -> Self {
+        Self {
+            joint,
+            position: None,
+            rotation: None,
+            scale: None,
+        }
+    }
+
+    pub fn with_position(mut self, track: Track<Vec3>) -> Self {
+        self.position = Some(track);
+        self
+    }
+
+    pub fn with_rotation(mut self, track: Track<Quat>) -> Self {
+        self.rotation = Some(track);
+        self
+    }
+
+    pub fn with_scale(mut self, track: Track<Vec3>) -> Self {
+        self.scale = Some(track);
+        self
+    }
+
+    fn duration(&self) -> f32 {
+        [
+            self.position.as_ref().map(|t| t.duration()),
+            self.rotation.as_ref().map(|t| t.duration()),
+            self.scale.as_ref().map(|t| t.duration()),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f32::max)
+    }
+}
+
+/// Keyframed joint transforms, e.g. a walk cycle
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    name: String,
+    channels: Vec<JointChannel>,
+    loop_mode: LoopMode,
+}
+
+impl AnimationClip {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            channels: Vec::new(),
+            loop_mode: LoopMode::Loop,
+        }
+    }
+
+    pub fn with_channel(mut self, channel: JointChannel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .map(|channel| channel.duration())
+            .fold(0.0, f32::max)
+    }
+
+    /// Samples the clip on top of the rest pose of `skeleton`
+    pub fn sample(&self, skeleton: &Skeleton, time: f32) -> Pose {
+        let mut pose = skeleton.rest_pose();
+        self.sample_into(&mut pose, time);
+        pose
+    }
+
+    /// Overrides the animated joints of `pose`, channels of joints missing from it are ignored
+    pub fn sample_into(&self, pose: &mut Pose, time: f32) {
+        let time = self.loop_mode.wrap(time, self.duration());
+        for channel in self.channels.iter() {
+            let Some(local) = pose.locals.get_mut(channel.joint) else {
+                continue;
+            };
+            if let Some(position) = channel.position.as_ref().and_then(|t| t.sample(time)) {
+                local.translation = position;
+            }
+            if let Some(rotation) = channel.rotation.as_ref().and_then(|t| t.sample(time)) {
+                local.rotation = rotation;
+            }
+            if let Some(scale) = channel.scale.as_ref().and_then(|t| t.sample(time)) {
+                local.scale = scale;
+            }
+        }
+    }
+}
+
+struct ClipState {
+    clip: usize,
+    time: f32,
+}
+
+/// Plays clips and cross-fades between them
+pub struct Animator {
+    clips: Vec<AnimationClip>,
+    current: Option<ClipState>,
+    previous: Option<ClipState>,
+    fade_duration: f32,
+    fade_elapsed: f32,
+    speed: f32,
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self {
+            clips: Vec::new(),
+            current: None,
+            previous: None,
+            fade_duration: 0.0,
+            fade_elapsed: 0.0,
+            speed: 1.0,
+        }
+    }
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_clip(mut self, clip: AnimationClip) -> Self {
+        self.clips.push(clip);
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn clips(&self) -> &[AnimationClip] {
+        &self.clips
+    }
+
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+
+    /// Unknown clips are ignored
+    pub fn play(&mut self, clip: usize) -> &mut Self {
+        if clip >= self.clips.len() {
+            return self;
+        }
+        self.current = Some(ClipState { clip, time: 0.0 });
+        self.previous = None;
+        self
+    }
+
+    /// Starts `clip` while fading out the current one over `duration` seconds, unknown clips are ignored
+    pub fn crossfade(&mut self, clip: usize, duration: f32) -> &mut Self {
+        if clip >= self.clips.len() {
+            return self;
+        }
+        self.previous = self.current.take();
+        self.current = Some(ClipState { clip, time: 0.0 });
+        self.fade_duration = duration;
+        self.fade_elapsed = 0.0;
+        self
+    }
+
+    pub fn advance(&mut self, dt: f32) -> &mut Self {
+        let dt = dt * self.speed;
+        if let Some(current) = self.current.as_mut() {
+            current.time += dt;
+        }
+        if let Some(previous) = self.previous.as_mut() {
+            previous.time += dt;
+            self.fade_elapsed += dt;
+            if self.fade_elapsed >= self.fade_duration {
+                self.previous = None;
+            }
+        }
+        self
+    }
+
+    pub fn update(&mut self, time_step: &TimeStep) -> &mut Self {
+        self.advance(time_step.delta_time())
+    }
+
+    pub fn pose(&self, skeleton: &Skeleton) -> Pose {
+        let current = match self.current.as_ref() {
+            Some(state) => self.clips[state.clip].sample(skeleton, state.time),
+            None => return skeleton.rest_pose(),
+        };
+
+        match self.previous.as_ref() {
+            Some(state) if self.fade_duration > 0.0 => {
+                let previous = self.clips[state.clip].sample(skeleton, state.time);
+                previous.blend(&current, self.fade_elapsed / self.fade_duration)
+            },
+            _ => current,
+        }
+    }
+}
+
+/// Joints influencing a vertex, unused slots have a zero weight
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SkinWeights {
+    pub joints: [u16; MAX_JOINT_INFLUENCES],
+    pub weights: [f32; MAX_JOINT_INFLUENCES],
+}
+
+impl SkinWeights {
+    pub fn single(joint: u16) -> Self {
+        Self {
+            joints: [joint, 0, 0, 0],
+            weights: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn normalized(mut self) -> Self {
+        let total: f32 = self.weights.iter().sum();
+        if total > 0.0 {
+            self.weights.iter_mut().for_each(|w| *w /= total);
+        }
+        self
+    }
+}
+
+/// A mesh deformed by a skeleton. Skinning is done on the CPU
+/// and the result is stored in an `Object` that can be drawn as usual.
+pub struct SkinnedMesh {
+    bind_vertices: Vec<Vertex>,
+    weights: Vec<SkinWeights>,
+    object: Object,
+}
+
+impl SkinnedMesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<i32>, weights: Vec<SkinWeights>) -> Self {
+        assert_eq!(vertices.len(), weights.len(), "one set of skin weights per vertex is required");
+
+        Self {
+            object: Object::new_mesh(vertices.clone(), indices),
+            bind_vertices: vertices,
+            weights: weights.into_iter().map(|w| w.normalized()).collect(),
+        }
+    }
+
+    pub fn object(&self) -> &Object {
+        &self.object
+    }
+
+    pub fn object_mut(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    pub fn weights(&self) -> &[SkinWeights] {
+        &self.weights
+    }
+
+    /// Influences of joints missing from the skeleton are ignored,
+    /// vertices left without any influence keep their bind pose
+    pub fn skin(&mut self, skeleton: &Skeleton, pose: &Pose) -> &mut Self {
+        let matrices = skeleton.skinning_matrices(pose);
+
+        let vertices = self.object.vertices_mut()
+            .iter_mut()
+            .zip(self.bind_vertices.iter())
+            .zip(self.weights.iter());
+        for ((vertex, bind), weights) in vertices {
+            let mut skin = Mat4::ZERO;
+            let mut total = 0.0;
+            for (joint, weight) in weights.joints.iter().zip(weights.weights.iter()) {
+                if let Some(matrix) = matrices.get(*joint as usize).filter(|_| *weight > 0.0) {
+                    skin += *matrix * *weight;
+                    total += *weight;
+                }
+            }
+            let skin = if total > 0.0 { skin * (1.0 / total) } else { Mat4::IDENTITY };
+
+            // the inverse transpose keeps the normals perpendicular under non-uniform scale
+            let linear = Mat3::from_mat4(skin);
+            let normal_matrix = if linear.determinant().abs() > f32::EPSILON {
+                linear.inverse().transpose()
+            } else {
+                linear
+            };
+            let position = skin.transform_point3(Vec3::from(bind.position));
+            let normal = (normal_matrix * Vec3::from(bind.normal)).normalize_or_zero();
+            vertex.position = position.to_array();
+            vertex.normal = normal.to_array();
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use glam::vec3;
+
+    use crate::animation::Easing;
+
+    use super::*;
+
+    fn arm() -> Skeleton {
+        let mut skeleton = Skeleton::new();
+        let shoulder = skeleton.add_joint("shoulder", None, Transform::new());
+        skeleton.add_joint("elbow", Some(shoulder), Transform::new().with_translation(vec3(1.0, 0.0, 0.0)));
+        skeleton
+    }
+
+    fn vertex(position: [f32; 3]) -> Vertex {
+        Vertex {
+            position,
+            color: [1.0; 4],
+            normal: [0.0, 1.0, 0.0],
+            uv: [0.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn bind_pose_leaves_the_mesh_untouched() {
+        let skeleton = arm();
+        let mut mesh = SkinnedMesh::new(
+            vec![vertex([2.0, 0.0, 0.0])],
+            vec![0],
+            vec![SkinWeights::single(1)]
+        );
+        mesh.skin(&skeleton, &skeleton.rest_pose());
+        assert!(Vec3::from(mesh.object().vertices()[0].position).abs_diff_eq(vec3(2.0, 0.0, 0.0), 1.0e-5));
+    }
+
+    #[test]
+    fn vertices_follow_their_joints() {
+        let skeleton = arm();
+        let mut pose = skeleton.rest_pose();
+        pose.local_mut(1).rotate_z(PI / 2.0);
+
+        let mut mesh = SkinnedMesh::new(
+            vec![vertex([2.0, 0.0, 0.0]), vertex([2.0, 0.0, 0.0])],
+            vec![0, 1],
+            vec![
+                SkinWeights::single(1),
+                SkinWeights { joints: [0, 1, 0, 0], weights: [1.0, 1.0, 0.0, 0.0] },
+            ]
+        );
+        mesh.skin(&skeleton, &pose);
+        let vertices = mesh.object().vertices();
+        assert!(Vec3::from(vertices[0].position).abs_diff_eq(vec3(1.0, 1.0, 0.0), 1.0e-5));
+        assert!(Vec3::from(vertices[1].position).abs_diff_eq(vec3(1.5, 0.5, 0.0), 1.0e-5));
+    }
+
+    #[test]
+    fn invalid_weights_and_scaled_normals() {
+        let skeleton = arm();
+        let mut pose = skeleton.rest_pose();
+        pose.local_mut(0).scale(vec3(2.0, 1.0, 1.0));
+
+        let mut slanted = vertex([1.0, 0.0, 0.0]);
+        slanted.normal = vec3(1.0, 1.0, 0.0).normalize().to_array();
+        let mut mesh = SkinnedMesh::new(
+            vec![vertex([1.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0]), slanted],
+            vec![0, 1, 2],
+            vec![
+                // joint 7 doesn't exist, joint 0 keeps its whole weight
+                SkinWeights { joints: [7, 0, 0, 0], weights: [0.5, 0.5, 0.0, 0.0] },
+                SkinWeights { joints: [0; 4], weights: [0.0; 4] },
+                SkinWeights::single(0),
+            ]
+        );
+        mesh.skin(&skeleton, &pose);
+        let vertices = mesh.object().vertices();
+        assert!(Vec3::from(vertices[0].position).abs_diff_eq(vec3(2.0, 0.0, 0.0), 1.0e-5));
+        // no influence, the bind pose is kept
+        assert!(Vec3::from(vertices[1].position).abs_diff_eq(vec3(1.0, 0.0, 0.0), 1.0e-5));
+        // still perpendicular to the stretched surface
+        assert!(Vec3::from(vertices[2].normal).abs_diff_eq(vec3(0.5, 1.0, 0.0).normalize(), 1.0e-5));
+    }
+
+    #[test]
+    fn crossfading_blends_the_clips() {
+        let skeleton = arm();
+        let raise = AnimationClip::new("raise")
+            .with_channel(
+                JointChannel::new(0).with_position(
+                    Track::new()
+                        .with_keyframe(0.0, vec3(0.0, 2.0, 0.0), Easing::Linear)
+                        .with_keyframe(1.0, vec3(0.0, 2.0, 0.0), Easing::Linear)
+                )
+            );
+        let mut animator = Animator::new()
+            .with_clip(AnimationClip::new("idle"))
+            .with_clip(raise);
+
+        animator.play(0).advance(0.5);
+        animator.crossfade(1, 1.0).advance(0.5);
+        let pose = animator.pose(&skeleton);
+        assert!(pose.locals()[0].translation.abs_diff_eq(vec3(0.0, 1.0, 0.0), 1.0e-5));
+
+        animator.advance(1.0);
+        let pose = animator.pose(&skeleton);
+        assert!(pose.locals()[0].translation.abs_diff_eq(vec3(0.0, 2.0, 0.0), 1.0e-5));
+    }
+
+    #[test]
+    fn unknown_clips_and_joints_are_ignored() {
+        let skeleton = arm();
+        let stray = AnimationClip::new("stray")
+            .with_channel(
+                JointChannel::new(5).with_position(
+                    Track::new().with_keyframe(0.0, vec3(0.0, 2.0, 0.0), Easing::Linear)
+                )
+            );
+        let mut animator = Animator::new().with_clip(stray);
+
+        animator.play(3).crossfade(4, 1.0).advance(0.5);
+        assert_eq!(animator.pose(&skeleton), skeleton.rest_pose());
+
+        animator.play(0).advance(0.5);
+        assert_eq!(animator.pose(&skeleton), skeleton.rest_pose());
+    }
+}
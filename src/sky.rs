@@ -0,0 +1,125 @@
+use glam::{vec3, Vec3};
+
+use crate::{animation::Interpolate, color::Color, texture::Cubemap};
+
+/// Cells of the starfield along each cube face, a star is at most one image pixel
+pub const STAR_CELLS: f32 = 128.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sun {
+    /// Direction from the camera toward the sun
+    pub direction: Vec3,
+    pub color: Color,
+    /// Angular radius in radians
+    pub radius: f32,
+}
+
+/// Background drawn behind the 3D geometry of a camera
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sky {
+    Gradient {
+        zenith: Color,
+        horizon: Color,
+        ground: Color,
+        sun: Option<Sun>,
+    },
+    Starfield {
+        background: Color,
+        /// Share of the cells holding a star
+        density: f32,
+        seed: u32,
+    },
+    Cubemap(Cubemap),
+}
+
+/// Same hash as the sky shader, used to place the stars
+fn hash(cell: Vec3, seed: f32) -> f32 {
+    ((cell.dot(vec3(12.9898, 78.233, 37.719)) + seed).sin() * 43_758.547).rem_euclid(1.0)
+}
+
+impl Sky {
+    pub fn gradient(zenith: Color, horizon: Color, ground: Color) -> Self {
+        Sky::Gradient { zenith, horizon, ground, sun: None }
+    }
+
+    pub fn starfield(background: Color, density: f32) -> Self {
+        Sky::Starfield { background, density, seed: 1 }
+    }
+
+    pub fn cubemap(cubemap: Cubemap) -> Self {
+        Sky::Cubemap(cubemap)
+    }
+
+    /// Adds a sun disc to a gradient sky, other skies are left unchanged
+    pub fn with_sun(mut self, direction: Vec3, color: Color, radius: f32) -> Self {
+        if let Sky::Gradient { sun, .. } = &mut self {
+            *sun = Some(Sun { direction: direction.normalize_or_zero(), color, radius });
+        }
+        self
+    }
+
+    pub fn with_seed(mut self, new_seed: u32) -> Self {
+        if let Sky::Starfield { seed, .. } = &mut self {
+            *seed = new_seed;
+        }
+        self
+    }
+
+    /// Color seen looking in `direction`, as drawn by the sky shader
+    pub fn color(&self, direction: Vec3) -> Color {
+        let direction = direction.normalize_or_zero();
+        match self {
+            Sky::Gradient { zenith, horizon, ground, sun } => {
+                if let Some(sun) = sun {
+                    if direction.dot(sun.direction) > sun.radius.cos() {
+                        return sun.color;
+                    }
+                }
+                if direction.y >= 0.0 {
+                    horizon.interpolate(zenith, direction.y.sqrt())
+                } else {
+                    horizon.interpolate(ground, (-direction.y * 4.0).min(1.0))
+                }
+            },
+            Sky::Starfield { background, density, seed } => {
+                let projected = direction / direction.abs().max_element();
+                let cell = (projected * STAR_CELLS).floor();
+                let seed = (*seed % 1000) as f32;
+                if hash(cell, seed) < *density {
+                    background.interpolate(&Color::white(), hash(cell + 0.5, seed))
+                } else {
+                    *background
+                }
+            },
+            Sky::Cubemap(cubemap) => cubemap.sample(direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::Texture;
+
+    use super::*;
+
+    #[test]
+    fn gradient_and_sun() {
+        let sky = Sky::gradient(Color::blue(), Color::white(), Color::black())
+            .with_sun(vec3(1.0, 1.0, 0.0), Color::red(), 0.1);
+        assert_eq!(sky.color(Vec3::Y), Color::blue());
+        assert_eq!(sky.color(vec3(0.0, 0.0, -1.0)), Color::white());
+        assert_eq!(sky.color(-Vec3::Y), Color::black());
+        assert_eq!(sky.color(vec3(1.0, 1.05, 0.0)), Color::red());
+    }
+
+    #[test]
+    fn cubemap_faces() {
+        let colors = [Color::red(), Color::green(), Color::blue(), Color::white(), Color::black(), Color::new(1.0, 1.0, 0.0, 1.0)];
+        let cubemap = Cubemap::new(colors.map(|color| Texture::from_color(2, 2, color)));
+        let sky = Sky::cubemap(cubemap);
+        let directions = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+        for (direction, color) in directions.iter().zip(colors) {
+            assert_eq!(sky.color(*direction + vec3(0.1, 0.2, 0.1)), color);
+        }
+    }
+}
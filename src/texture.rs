@@ -0,0 +1,254 @@
+use std::{rc::{Rc, Weak}, sync::atomic::{AtomicU64, Ordering}};
+
+use glam::{uvec2, UVec2, Vec3};
+
+use crate::color::Color;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// RGBA8 image kept on the CPU, the renderer uploads it the first time it is drawn
+/// and again after each change. Clones are cheap and share the pixels until one is modified,
+/// the modified clone then becomes a texture of its own with a new id.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    id: u64,
+    version: u64,
+    size: UVec2,
+    pixels: Rc<Vec<u8>>,
+    render_target: bool,
+    /// Shared by the clones, the renderer drops the GPU copy once they are all gone
+    lifetime: Rc<()>,
+}
+
+impl PartialEq for Texture {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.version == other.version
+    }
+}
+
+impl Texture {
+    /// `pixels` holds the rows from top to bottom, 4 bytes per pixel
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), (width * height * 4) as usize, "texture data doesn't match its size");
+        Self {
+            id: next_id(),
+            version: next_id(),
+            size: uvec2(width, height),
+            pixels: Rc::new(pixels),
+            render_target: false,
+            lifetime: Rc::new(()),
+        }
+    }
+
+    /// Texture drawn by the cameras targeting it, its pixels only exist on the GPU.
+    /// The targets are rendered before the image, a camera sees its own target as white.
+    pub fn render_target(width: u32, height: u32) -> Self {
+        assert!(width > 0 && height > 0, "a render target can't be empty");
+        Self {
+            id: next_id(),
+            version: next_id(),
+            size: uvec2(width, height),
+            pixels: Rc::new(Vec::new()),
+            render_target: true,
+            lifetime: Rc::new(()),
+        }
+    }
+
+    pub fn is_render_target(&self) -> bool {
+        self.render_target
+    }
+
+    pub fn from_color(width: u32, height: u32, color: Color) -> Self {
+        let pixel = Self::color_bytes(color);
+        Self::new(width, height, pixel.repeat((width * height) as usize))
+    }
+
+    pub fn from_fn(width: u32, height: u32, mut f: impl FnMut(u32, u32) -> Color) -> Self {
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&Self::color_bytes(f(x, y)));
+            }
+        }
+        Self::new(width, height, pixels)
+    }
+
+    fn color_bytes(color: Color) -> [u8; 4] {
+        color.as_array().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    /// Identifies the texture on the GPU, shared by clones as long as they share their pixels
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Changes each time the pixels are modified
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Can't be upgraded once the texture and all its clones are dropped
+    pub(crate) fn lifetime(&self) -> Weak<()> {
+        Rc::downgrade(&self.lifetime)
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size.x
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size.y
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// `None` outside of the texture and for render targets
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if self.render_target || x >= self.size.x || y >= self.size.y {
+            return None;
+        }
+        let i = ((y * self.size.x + x) * 4) as usize;
+        let [r, g, b, a] = [0, 1, 2, 3].map(|c| self.pixels[i + c] as f32 / 255.0);
+        Some(Color::new(r, g, b, a))
+    }
+
+    /// Rewriting the same color keeps the version, the texture isn't copied nor uploaded again
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) -> &mut Self {
+        if !self.render_target && x < self.size.x && y < self.size.y {
+            let i = ((y * self.size.x + x) * 4) as usize;
+            let bytes = Self::color_bytes(color);
+            if self.pixels[i..i + 4] != bytes {
+                self.pixels_mut()[i..i + 4].copy_from_slice(&bytes);
+            }
+        }
+        self
+    }
+
+    /// Gives access to the raw pixels, the texture is uploaded again on its next draw.
+    /// A texture sharing its pixels with clones gets a copy of them and a new id.
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        self.version = next_id();
+        if !self.render_target && Rc::get_mut(&mut self.pixels).is_none() {
+            self.id = next_id();
+            self.lifetime = Rc::new(());
+        }
+        Rc::make_mut(&mut self.pixels).as_mut_slice()
+    }
+}
+
+/// Six square textures seen around the camera, in the +x, -x, +y, -y, +z, -z order
+#[derive(Debug, Clone)]
+pub struct Cubemap {
+    id: u64,
+    size: u32,
+    faces: Rc<[Texture; 6]>,
+    lifetime: Rc<()>,
+}
+
+impl PartialEq for Cubemap {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Cubemap {
+    pub fn new(faces: [Texture; 6]) -> Self {
+        let size = faces[0].width();
+        assert!(
+            faces.iter().all(|face| face.size() == uvec2(size, size)),
+            "cubemap faces must be squares of the same size"
+        );
+        Self {
+            id: next_id(),
+            size,
+            faces: Rc::new(faces),
+            lifetime: Rc::new(()),
+        }
+    }
+
+    /// Identifies the cubemap on the GPU, shared by clones
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Can't be upgraded once the cubemap and all its clones are dropped
+    pub(crate) fn lifetime(&self) -> Weak<()> {
+        Rc::downgrade(&self.lifetime)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn faces(&self) -> &[Texture; 6] {
+        &self.faces
+    }
+
+    /// Color seen looking in `direction`, with the OpenGL face orientations
+    pub fn sample(&self, direction: Vec3) -> Color {
+        let abs = direction.abs();
+        let (face, s, t, major) = if abs.x >= abs.y && abs.x >= abs.z {
+            if direction.x > 0.0 { (0, -direction.z, -direction.y, abs.x) } else { (1, direction.z, -direction.y, abs.x) }
+        } else if abs.y >= abs.z {
+            if direction.y > 0.0 { (2, direction.x, direction.z, abs.y) } else { (3, direction.x, -direction.z, abs.y) }
+        } else if direction.z > 0.0 {
+            (4, direction.x, -direction.y, abs.z)
+        } else {
+            (5, -direction.x, -direction.y, abs.z)
+        };
+        let texel = |coord: f32| (((coord / major + 1.0) * 0.5 * self.size as f32) as u32).min(self.size - 1);
+        self.faces[face].pixel(texel(s), texel(t)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modified_clones_get_their_own_id() {
+        let mut texture = Texture::from_color(2, 2, Color::red());
+        let shared = texture.clone();
+        assert_eq!(texture, shared);
+
+        texture.set_pixel(1, 0, Color::blue());
+        assert_ne!(texture.id(), shared.id());
+        assert_ne!(texture.version(), shared.version());
+        assert_eq!(texture.pixel(1, 0).unwrap().as_array(), Color::blue().as_array());
+        assert_eq!(shared.pixel(1, 0).unwrap().as_array(), Color::red().as_array());
+        assert!(texture.pixel(2, 0).is_none());
+
+        let (id, version) = (texture.id(), texture.version());
+        texture.set_pixel(1, 0, Color::blue());
+        assert_eq!(texture.version(), version);
+
+        // no clone left to copy the pixels from, only the version changes
+        texture.set_pixel(0, 0, Color::blue());
+        assert_eq!(texture.id(), id);
+        assert_ne!(texture.version(), version);
+    }
+
+    #[test]
+    fn lifetime_ends_with_the_last_clone() {
+        let mut texture = Texture::from_color(2, 2, Color::red());
+        let lifetime = texture.lifetime();
+        let shared = texture.clone();
+        texture.set_pixel(0, 0, Color::blue());
+        assert!(texture.lifetime().upgrade().is_some());
+        assert!(!texture.lifetime().ptr_eq(&lifetime));
+        drop(texture);
+        assert!(lifetime.upgrade().is_some());
+        drop(shared);
+        assert!(lifetime.upgrade().is_none());
+    }
+}
@@ -52,4 +52,54 @@ impl TimeStep {
     pub fn fps(&self) -> u32 {
         self.fps
     }
-}
\ No newline at end of file
+}
+
+/// Accumulates frame times into a whole number of fixed steps, for deterministic simulations
+pub struct FixedStep {
+    step: f32,
+    accumulator: f32,
+    max_steps: u32,
+}
+
+impl Default for FixedStep {
+    fn default() -> Self {
+        Self::new(1.0 / 60.0)
+    }
+}
+
+impl FixedStep {
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+            max_steps: 8,
+        }
+    }
+
+    /// Upper bound of steps per frame, the remaining time is dropped after a long frame
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Number of steps to run for a frame of `dt` seconds
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+        steps
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Fraction of a step left in the accumulator, to interpolate between two steps
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
@@ -0,0 +1,79 @@
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_matrix(mat: &Mat4) -> Self {
+        let (scale, rotation, translation) = mat.to_scale_rotation_translation();
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn translate(&mut self, translation: Vec3) -> &mut Self {
+        self.translation += translation;
+        self
+    }
+
+    pub fn rotate_x(&mut self, angle: f32) -> &mut Self {
+        self.rotation *= Quat::from_rotation_x(angle);
+        self
+    }
+
+    pub fn rotate_y(&mut self, angle: f32) -> &mut Self {
+        self.rotation *= Quat::from_rotation_y(angle);
+        self
+    }
+
+    pub fn rotate_z(&mut self, angle: f32) -> &mut Self {
+        self.rotation *= Quat::from_rotation_z(angle);
+        self
+    }
+
+    pub fn scale(&mut self, scale: Vec3) -> &mut Self {
+        self.scale *= scale;
+        self
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}